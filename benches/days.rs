@@ -0,0 +1,109 @@
+//! `cargo bench -- --save-baseline before`, make a change, then
+//! `cargo bench -- --baseline before` to see what it did to each day.
+
+use std::time::Duration;
+
+use aoc_in_rust::{
+    watch::{run_part, Outcome},
+    FUNCTIONS, PARSERS,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// Anything slower than this on a single run isn't worth sampling.
+const TOO_SLOW: Duration = Duration::from_secs(5);
+
+/// Criterion options that take a value, so it isn't mistaken for the filter.
+const VALUE_FLAGS: &[&str] = &[
+    "-c",
+    "--color",
+    "-s",
+    "--save-baseline",
+    "-b",
+    "--baseline",
+    "--baseline-lenient",
+    "--load-baseline",
+    "--sample-size",
+    "--warm-up-time",
+    "--measurement-time",
+    "--nresamples",
+    "--noise-threshold",
+    "--confidence-level",
+    "--significance-level",
+    "--profile-time",
+    "--plotting-backend",
+    "--output-format",
+    "--format",
+];
+
+/// Every part gets run once up front to find the unfinished and very slow
+/// ones. That's minutes of work for the whole calendar, so only do it for
+/// the benchmarks the filter (`cargo bench -- day14`) would pick.
+fn filter() -> Option<String> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    args.iter()
+        .enumerate()
+        .find(|(i, arg)| {
+            !arg.starts_with('-') && (*i == 0 || !VALUE_FLAGS.contains(&args[i - 1].as_str()))
+        })
+        .map(|(_, arg)| arg.clone())
+}
+
+fn days(c: &mut Criterion) {
+    let filter = filter();
+    let wanted = |id: &str| filter.as_ref().is_none_or(|f| id.contains(f.as_str()));
+    // Criterion's own output buries a line here and there, so these are
+    // listed again at the end.
+    let mut skipped = vec![];
+    for (i, &(part1, part2)) in FUNCTIONS.iter().enumerate() {
+        let day = i as u8 + 1;
+        let Ok(input) = std::fs::read_to_string(format!("./input/day{}.txt", day)) else {
+            if wanted(&format!("day{day}/")) {
+                skipped.push(format!("day{day}: skipped, no input/day{day}.txt"));
+            }
+            continue;
+        };
+        let mut group = c.benchmark_group(format!("day{day}"));
+
+        if let Some((_, parse)) = PARSERS.iter().find(|(d, _)| *d == day) {
+            group.sample_size(100);
+            group.bench_function("parse", |b| b.iter(|| parse(&input)));
+        }
+
+        for (name, part) in [("part1", part1), ("part2", part2)] {
+            if !wanted(&format!("day{day}/{name}")) {
+                continue;
+            }
+            match run_part(part, &input) {
+                (Outcome::Panicked(message), _) => {
+                    skipped.push(format!("day{day}/{name}: skipped, {message}"));
+                    eprintln!("{}", skipped.last().unwrap());
+                }
+                (_, elapsed) if elapsed > TOO_SLOW => {
+                    skipped.push(format!(
+                        "day{day}/{name}: skipped, one run took {elapsed:.1?} (over {TOO_SLOW:?})"
+                    ));
+                    eprintln!("{}", skipped.last().unwrap());
+                }
+                (_, elapsed) => {
+                    group.sample_size(if elapsed > Duration::from_millis(50) {
+                        10
+                    } else {
+                        100
+                    });
+                    group.bench_function(name, |b| b.iter(|| part(&input)));
+                }
+            }
+        }
+        group.finish();
+    }
+
+    if !skipped.is_empty() {
+        eprintln!("\nNot benchmarked:");
+        for line in skipped {
+            eprintln!("  {line}");
+        }
+    }
+}
+
+criterion_group!(benches, days);
+criterion_main!(benches);
@@ -1,63 +1,160 @@
-fn words_to_digit_chars(line: &str) -> String {
-    // This was silly. After writing this I scrolled through reddit and saw
-    // suggestion to just replace "nine" with "n9ne", etc. :shrugging:
-    let mut output = "".to_string();
-    let mut current_word = "".to_string();
-    for (i, _) in line.char_indices() {
-        current_word.push_str(&line[i..=i]);
-        let digit = if current_word.contains("one") {
-            Some("1")
-        } else if current_word.contains("two") {
-            Some("2")
-        } else if current_word.contains("three") {
-            Some("3")
-        } else if current_word.contains("four") {
-            Some("4")
-        } else if current_word.contains("five") {
-            Some("5")
-        } else if current_word.contains("six") {
-            Some("6")
-        } else if current_word.contains("seven") {
-            Some("7")
-        } else if current_word.contains("eight") {
-            Some("8")
-        } else if current_word.contains("nine") {
-            Some("9")
-        } else {
-            None
-        };
-        if digit.is_some() {
-            current_word.remove(current_word.len() - 2);
-            output.push_str(digit.unwrap());
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Default)]
+struct Node {
+    next: HashMap<char, usize>,
+    fail: usize,
+    /// (length in chars, value) of every word ending here, including the
+    /// ones reached through fail links.
+    outputs: Vec<(usize, u32)>,
+}
+
+/// An Aho–Corasick automaton over a word → digit table. One pass over a
+/// line finds every word, overlapping ones included (`twone` is 2 then 1).
+#[derive(Debug)]
+pub struct Recognizer {
+    nodes: Vec<Node>,
+}
+
+impl Recognizer {
+    pub fn new(words: &[(&str, u32)]) -> Recognizer {
+        let mut nodes = vec![Node::default()];
+        for (word, value) in words {
+            let mut node = 0;
+            for c in word.chars() {
+                node = match nodes[node].next.get(&c) {
+                    Some(&next) => next,
+                    None => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[node].next.insert(c, next);
+                        next
+                    }
+                };
+            }
+            nodes[node].outputs.push((word.chars().count(), *value));
+        }
+
+        // Breadth first so a node's fail target is always finished before it.
+        let mut queue = nodes[0].next.values().copied().collect::<VecDeque<_>>();
+        while let Some(node) = queue.pop_front() {
+            let edges = nodes[node]
+                .next
+                .iter()
+                .map(|(c, next)| (*c, *next))
+                .collect::<Vec<_>>();
+            for (c, next) in edges {
+                let mut fail = nodes[node].fail;
+                let fail = loop {
+                    if let Some(&target) = nodes[fail].next.get(&c) {
+                        break target;
+                    }
+                    if fail == 0 {
+                        break 0;
+                    }
+                    fail = nodes[fail].fail;
+                };
+                nodes[next].fail = fail;
+                let inherited = nodes[fail].outputs.clone();
+                nodes[next].outputs.extend(inherited);
+                queue.push_back(next);
+            }
+        }
+        Recognizer { nodes }
+    }
+
+    /// Just the digit characters.
+    pub fn digits() -> Recognizer {
+        Recognizer::new(&DIGITS)
+    }
+
+    /// Digit characters and `one` through `nine`.
+    pub fn english() -> Recognizer {
+        let mut words = DIGITS.to_vec();
+        words.extend(ENGLISH);
+        Recognizer::new(&words)
+    }
+
+    /// Every match as (start char index, value), in the order they end.
+    pub fn matches<'a>(&'a self, line: &'a str) -> impl Iterator<Item = (usize, u32)> + 'a {
+        let mut node = 0;
+        line.chars().enumerate().flat_map(move |(i, c)| {
+            loop {
+                if let Some(&next) = self.nodes[node].next.get(&c) {
+                    node = next;
+                    break;
+                }
+                if node == 0 {
+                    break;
+                }
+                node = self.nodes[node].fail;
+            }
+            self.nodes[node]
+                .outputs
+                .iter()
+                .map(move |(length, value)| (i + 1 - length, *value))
+        })
+    }
+
+    /// The first and last digit in the line, by where their words start.
+    pub fn first_and_last(&self, line: &str) -> Option<(u32, u32)> {
+        let mut first: Option<(usize, u32)> = None;
+        let mut last: Option<(usize, u32)> = None;
+        for (start, value) in self.matches(line) {
+            if first.is_none_or(|(first_start, _)| start < first_start) {
+                first = Some((start, value));
+            }
+            if last.is_none_or(|(last_start, _)| start > last_start) {
+                last = Some((start, value));
+            }
         }
-        output.push_str(&line[i..=i]);
+        Some((first?.1, last?.1))
+    }
+
+    pub fn calibration_value(&self, line: &str) -> Option<u32> {
+        self.first_and_last(line)
+            .map(|(first, last)| first * 10 + last)
+    }
+
+    pub fn calibration_sum(&self, input: &str) -> u32 {
+        input
+            .lines()
+            .filter_map(|line| self.calibration_value(line))
+            .sum()
     }
-    output
 }
 
+const DIGITS: [(&str, u32); 10] = [
+    ("0", 0),
+    ("1", 1),
+    ("2", 2),
+    ("3", 3),
+    ("4", 4),
+    ("5", 5),
+    ("6", 6),
+    ("7", 7),
+    ("8", 8),
+    ("9", 9),
+];
+
+const ENGLISH: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
+
 pub fn day1_part1(input: &str) -> String {
-    let numbers = '0'..='9';
-    let temp = input.lines().map(|line| {
-        let digits = line
-            .chars()
-            .filter_map(|c| if numbers.contains(&c) { Some(c) } else { None })
-            .collect::<Vec<_>>();
-        let digit = digits[0].to_string() + digits.last().unwrap().to_string().as_str();
-        digit.parse::<u32>().unwrap()
-    });
-
-    let _ = temp.clone().collect::<Vec<_>>();
-
-    temp.sum::<u32>().to_string()
+    Recognizer::digits().calibration_sum(input).to_string()
 }
 
 pub fn day1_part2(input: &str) -> String {
-    let input = input
-        .lines()
-        .map(|line| words_to_digit_chars(line))
-        .collect::<Vec<_>>()
-        .join("\n");
-    day1_part1(input.as_str())
+    Recognizer::english().calibration_sum(input).to_string()
 }
 
 #[cfg(test)]
@@ -106,13 +203,25 @@ zoneight234
     }
 
     #[test]
-    fn test_words_to_digits() {
-        assert_eq!("seve7nin9e", words_to_digit_chars("sevenine"));
-        assert_eq!("eigh8tw2othre3e", words_to_digit_chars("eightwothree"));
+    fn test_overlapping_words() {
+        let recognizer = Recognizer::english();
+        assert_eq!(Some((2, 1)), recognizer.first_and_last("twone"));
+        assert_eq!(Some((8, 3)), recognizer.first_and_last("eightwothree"));
         assert_eq!(
-            "sseve7nhcltw2oseve7n2cxrmxxcr",
-            words_to_digit_chars("ssevenhcltwoseven2cxrmxxcr")
+            vec![(0, 8), (4, 2), (7, 3), (12, 4)],
+            recognizer.matches("eightwothree4").collect::<Vec<_>>()
         );
-        assert_eq!("eigh8tw2othre3e4", words_to_digit_chars("eightwothree4"));
+        assert_eq!(None, recognizer.first_and_last("abc"));
+    }
+
+    #[test]
+    fn test_other_vocabularies() {
+        let german = Recognizer::new(&[("eins", 1), ("zwei", 2), ("drei", 3), ("fünf", 5)]);
+        assert_eq!(Some(15), german.calibration_value("xeinsfünfy"));
+        assert_eq!(Some(31), german.calibration_value("dreins"));
+
+        let with_zero = Recognizer::new(&[("zero", 0), ("one", 1), ("9", 9)]);
+        assert_eq!(Some(1), with_zero.calibration_value("zerone"));
+        assert_eq!(Some(90), with_zero.calibration_value("9zero"));
     }
 }
@@ -1,9 +1,55 @@
-use std::collections::HashMap;
+use std::str::FromStr;
 
 #[derive(Debug, Eq, Hash, PartialEq, Clone, Copy)]
-struct Position {
-    x: i32,
-    y: i32,
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    fn step(self, p: Position) -> Position {
+        match self {
+            Direction::North => Position { x: p.x, y: p.y - 1 },
+            Direction::South => Position { x: p.x, y: p.y + 1 },
+            Direction::East => Position { x: p.x + 1, y: p.y },
+            Direction::West => Position { x: p.x - 1, y: p.y },
+        }
+    }
+
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// Which way `b` lies from `a`, if they share a row or column.
+    fn between(a: Position, b: Position) -> Option<Direction> {
+        match (a.x.cmp(&b.x), a.y.cmp(&b.y)) {
+            (std::cmp::Ordering::Equal, std::cmp::Ordering::Greater) => Some(Direction::North),
+            (std::cmp::Ordering::Equal, std::cmp::Ordering::Less) => Some(Direction::South),
+            (std::cmp::Ordering::Less, std::cmp::Ordering::Equal) => Some(Direction::East),
+            (std::cmp::Ordering::Greater, std::cmp::Ordering::Equal) => Some(Direction::West),
+            _ => None,
+        }
+    }
 }
 
 // | is a vertical pipe connecting north and south.
@@ -27,6 +73,15 @@ enum Pipe {
 }
 
 impl Pipe {
+    const SHAPES: [Pipe; 6] = [
+        Pipe::Vertical,
+        Pipe::Horizontal,
+        Pipe::UpAndRight,
+        Pipe::UpAndLeft,
+        Pipe::DownAndLeft,
+        Pipe::DownAndRight,
+    ];
+
     fn from_char(c: char) -> Option<Pipe> {
         use Pipe::*;
         match c {
@@ -38,280 +93,257 @@ impl Pipe {
             '7' => Some(DownAndLeft),
             'F' => Some(DownAndRight),
             '.' => Some(Air),
-            _ => unreachable!(),
+            _ => None,
+        }
+    }
+
+    fn glyph(self, double: bool) -> char {
+        let glyphs = if double {
+            "║═╚╝╗╔"
+        } else {
+            "│─└┘┐┌"
+        };
+        match Pipe::SHAPES.iter().position(|shape| *shape == self) {
+            Some(i) => glyphs.chars().nth(i).unwrap(),
+            None => ' ',
+        }
+    }
+
+    /// The start could be any shape, so it's open every way until we know.
+    fn openings(self) -> &'static [Direction] {
+        use Direction::*;
+        match self {
+            Pipe::Start => &Direction::ALL,
+            Pipe::Air => &[],
+            Pipe::Vertical => &[North, South],
+            Pipe::Horizontal => &[East, West],
+            Pipe::UpAndRight => &[North, East],
+            Pipe::UpAndLeft => &[North, West],
+            Pipe::DownAndLeft => &[South, West],
+            Pipe::DownAndRight => &[South, East],
         }
     }
 }
 
 fn is_connected(a: (Position, Pipe), b: (Position, Pipe)) -> bool {
-    use Pipe::*;
-
-    if !(a.0.x == b.0.x || a.0.y == b.0.y) {
+    if a.1 == Pipe::Start && b.1 == Pipe::Start {
         return false;
     }
+    let Some(direction) = Direction::between(a.0, b.0) else {
+        return false;
+    };
+    a.1.openings().contains(&direction) && b.1.openings().contains(&direction.opposite())
+}
 
-    // This is not fun. I haven't thought through it but there's surely simpler logic
-    // to achieve what's needed :sweatsmile:
-    match (a.1, b.1) {
-        (Air, _) => false,
-        (_, Air) => false,
-        (Start, Start) => false,
-        (Start, Vertical) => b.0.x == a.0.x,
-        (Start, Horizontal) => a.0.y == b.0.y,
-        (Start, UpAndRight) => a.0.y < b.0.y || a.0.x > b.0.x,
-        (Start, UpAndLeft) => a.0.y < b.0.y || a.0.x < b.0.x,
-        (Start, DownAndLeft) => a.0.y > b.0.y || a.0.x < b.0.x,
-        (Start, DownAndRight) => a.0.y > b.0.y || a.0.x > b.0.x,
-        (Vertical, Start) | (Vertical, Vertical) => a.0.x == b.0.x,
-        (Vertical, Horizontal) => false,
-        (Vertical, UpAndRight) | (Vertical, UpAndLeft) => a.0.y < b.0.y,
-        (Vertical, DownAndLeft) | (Vertical, DownAndRight) => a.0.y > b.0.y,
-        (Horizontal, Vertical) => false,
-        (Horizontal, Start) | (Horizontal, Horizontal) => a.0.y == b.0.y,
-        (Horizontal, UpAndRight) | (Horizontal, DownAndRight) => a.0.x > b.0.x,
-        (Horizontal, UpAndLeft) | (Horizontal, DownAndLeft) => a.0.x < b.0.x,
-        (UpAndRight, Start) => a.0.x < b.0.x || a.0.y > b.0.y,
-        (UpAndRight, Vertical) => a.0.y > b.0.y,
-        (UpAndRight, Horizontal) => a.0.x < b.0.x,
-        (UpAndRight, UpAndRight) => false,
-        (UpAndRight, UpAndLeft) => a.0.x < b.0.x,
-        (UpAndRight, DownAndLeft) => a.0.x < b.0.x || a.0.y > b.0.y,
-        (UpAndRight, DownAndRight) => a.0.y > b.0.y,
-        (UpAndLeft, Start) => a.0.x < b.0.x || a.0.y > b.0.y,
-        (UpAndLeft, Vertical) => a.0.y > b.0.y,
-        (UpAndLeft, Horizontal) => a.0.x > b.0.x,
-        (UpAndLeft, UpAndRight) => a.0.x > b.0.x,
-        (UpAndLeft, UpAndLeft) => false,
-        (UpAndLeft, DownAndLeft) => a.0.y > b.0.y,
-        (UpAndLeft, DownAndRight) => a.0.y > b.0.y || b.0.x < a.0.x,
-        (DownAndLeft, Start) => a.0.x > b.0.x || a.0.y < b.0.y,
-        (DownAndLeft, Vertical) => a.0.y < b.0.y,
-        (DownAndLeft, Horizontal) => b.0.x < a.0.x,
-        (DownAndLeft, UpAndRight) => a.0.y < b.0.y || a.0.x > b.0.x,
-        (DownAndLeft, UpAndLeft) => a.0.y < b.0.y,
-        (DownAndLeft, DownAndLeft) => false,
-        (DownAndLeft, DownAndRight) => b.0.x < a.0.x,
-        (DownAndRight, Start) => b.0.x > a.0.x || b.0.y > a.0.y,
-        (DownAndRight, Vertical) => a.0.y < b.0.y,
-        (DownAndRight, Horizontal) => b.0.x > a.0.x,
-        (DownAndRight, UpAndRight) => b.0.y > a.0.y,
-        (DownAndRight, UpAndLeft) => b.0.y > a.0.y || a.0.x < b.0.x,
-        (DownAndRight, DownAndLeft) => b.0.x > a.0.x,
-        (DownAndRight, DownAndRight) => false,
-    }
+/// The sketch with the start's real shape filled in, and the main loop it sits on.
+#[derive(Debug, Clone)]
+pub struct PipeMaze {
+    width: usize,
+    height: usize,
+    tiles: Vec<Pipe>,
+    start: Position,
+    main_loop: Vec<Position>,
 }
 
-pub fn day10_part1(input: &str) -> String {
-    // Parse the map in to a starting (x, y) and a HashMap of (x, y) => Pipe
-    // Walk from the start following valid moves and count how many before we return to the start
-    // divide that by two and its the answer
-    let map = input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.chars().enumerate().filter_map(move |(x, c)| {
-                let pipe = Pipe::from_char(c);
-                if pipe.is_some_and(|p| p != Pipe::Air) {
-                    Some((
-                        Position {
-                            x: x as i32,
-                            y: y as i32,
-                        },
-                        pipe.unwrap(),
-                    ))
-                } else {
-                    None
+impl FromStr for PipeMaze {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let height = input.lines().count();
+        let width = input
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut tiles = vec![Pipe::Air; width * height];
+        let mut start = None;
+        for (y, line) in input.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let pipe = Pipe::from_char(c).ok_or(format!("unknown tile '{c}' at ({x}, {y})"))?;
+                if pipe == Pipe::Start {
+                    start = Some(Position {
+                        x: x as i32,
+                        y: y as i32,
+                    });
                 }
-            })
-        })
-        .collect::<HashMap<Position, Pipe>>();
-    let start = map
-        .iter()
-        .find(|item| {
-            if let Pipe::Start = item.1 {
-                true
-            } else {
-                false
+                tiles[y * width + x] = pipe;
             }
-        })
-        .unwrap();
+        }
+        let start = start.ok_or("no start tile")?;
 
-    let mut curr = (start.0.clone(), start.1.clone());
-    let mut prev = *start.0;
-    let mut steps = 0;
-    loop {
-        steps += 1;
-        // look left, right, up, down
-        let positions = vec![
-            Position {
-                x: curr.0.x - 1,
-                y: curr.0.y,
-            },
-            Position {
-                x: curr.0.x + 1,
-                y: curr.0.y,
-            },
-            Position {
-                x: curr.0.x,
-                y: curr.0.y + 1,
-            },
-            Position {
-                x: curr.0.x,
-                y: curr.0.y - 1,
-            },
-        ];
-        let next = positions
-            .iter()
-            .filter(|p| **p != prev)
-            .find(|position| match map.get(position) {
-                Some(p) => {
-                    if is_connected(curr, (**position, p.clone())) {
-                        true
-                    } else {
-                        false
-                    }
-                }
-                None => false,
+        let mut maze = PipeMaze {
+            width,
+            height,
+            tiles,
+            start,
+            main_loop: vec![],
+        };
+        // Same order openings() lists them in, so the shapes compare directly.
+        let connections = Direction::ALL
+            .into_iter()
+            .filter(|direction| {
+                let next = direction.step(start);
+                is_connected((start, Pipe::Start), (next, maze.pipe_at(next)))
             })
-            .unwrap();
-        prev = curr.0.clone();
-        curr = (*next, *map.get(next).unwrap());
+            .collect::<Vec<_>>();
+        let shape = Pipe::SHAPES
+            .into_iter()
+            .find(|shape| shape.openings() == connections)
+            .ok_or(format!("the start connects {} ways", connections.len()))?;
+        let index = maze.index(start).unwrap();
+        maze.tiles[index] = shape;
 
-        if Pipe::Start == curr.1 {
-            break;
+        let mut position = start;
+        let mut heading = shape.openings()[0];
+        loop {
+            maze.main_loop.push(position);
+            position = heading.step(position);
+            if position == start {
+                break;
+            }
+            // The pipe has to open back the way we came before there's any
+            // point looking for the way out.
+            let openings = maze.pipe_at(position).openings();
+            heading = *openings
+                .iter()
+                .find(|d| **d != heading.opposite())
+                .filter(|_| openings.contains(&heading.opposite()))
+                .ok_or(format!(
+                    "the loop breaks at ({}, {})",
+                    position.x, position.y
+                ))?;
         }
+        Ok(maze)
     }
-    (steps / 2).to_string()
 }
 
-pub fn day10_part2(input: &str) -> String {
-    // SHAMELESS COPY OF PART1 FOR NOW
-    // We need the path so we can filter out the junk pipes which threw me off :(
-    // I needed lots of hand holding on this one. Leaned on https://www.youtube.com/watch?v=_-QXvb8GJlg
-    let map = input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.chars().enumerate().filter_map(move |(x, c)| {
-                let pipe = Pipe::from_char(c);
-                if pipe.is_some() {
-                    Some((
-                        Position {
-                            x: x as i32,
-                            y: y as i32,
-                        },
-                        pipe.unwrap(),
-                    ))
+impl PipeMaze {
+    fn index(&self, p: Position) -> Option<usize> {
+        let in_bounds =
+            (0..self.width as i32).contains(&p.x) && (0..self.height as i32).contains(&p.y);
+        in_bounds.then(|| p.y as usize * self.width + p.x as usize)
+    }
+
+    fn pipe_at(&self, p: Position) -> Pipe {
+        self.index(p).map_or(Pipe::Air, |i| self.tiles[i])
+    }
+
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    /// Every tile of the main loop in order, starting at the start.
+    pub fn loop_tiles(&self) -> &[Position] {
+        &self.main_loop
+    }
+
+    /// Just the corners of the main loop, in order.
+    pub fn vertices(&self) -> Vec<Position> {
+        self.main_loop
+            .iter()
+            .copied()
+            .filter(|p| !matches!(self.pipe_at(*p), Pipe::Vertical | Pipe::Horizontal))
+            .collect()
+    }
+
+    pub fn loop_length(&self) -> usize {
+        self.main_loop.len()
+    }
+
+    /// The loop tile furthest along the loop from the start, and how far it is.
+    pub fn farthest(&self) -> (Position, usize) {
+        let steps = self.main_loop.len() / 2;
+        (self.main_loop[steps], steps)
+    }
+
+    /// Tiles inside the loop. The shoelace formula gives the loop's area and
+    /// Pick's theorem takes the boundary tiles back off it, `i = A - b/2 + 1`.
+    pub fn enclosed(&self) -> usize {
+        let vertices = self.vertices();
+        let twice_area = vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .map(|(a, b)| a.x as i64 * b.y as i64 - b.x as i64 * a.y as i64)
+            .sum::<i64>()
+            .unsigned_abs() as usize;
+        // Adding the 2 first keeps a loop with nothing inside from underflowing.
+        (twice_area + 2 - self.loop_length()) / 2
+    }
+
+    fn on_loop(&self) -> Vec<bool> {
+        let mut on_loop = vec![false; self.tiles.len()];
+        for p in &self.main_loop {
+            on_loop[self.index(*p).unwrap()] = true;
+        }
+        on_loop
+    }
+
+    /// Which tiles are inside the loop, row by row. Scanning along a row, the
+    /// inside flips at every loop pipe that reaches north.
+    fn insides(&self) -> Vec<bool> {
+        let on_loop = self.on_loop();
+        let mut insides = vec![false; self.tiles.len()];
+        for row in 0..self.height {
+            let mut inside = false;
+            for i in row * self.width..(row + 1) * self.width {
+                if on_loop[i] {
+                    inside ^= self.tiles[i].openings().contains(&Direction::North);
                 } else {
-                    unreachable!();
+                    insides[i] = inside;
                 }
-            })
-        })
-        .collect::<HashMap<Position, Pipe>>();
-    let start = map
-        .iter()
-        .find(|item| {
-            if let Pipe::Start = item.1 {
-                true
-            } else {
-                false
             }
-        })
-        .unwrap();
+        }
+        insides
+    }
 
-    let mut path: HashMap<Position, Pipe> = HashMap::new();
-    let mut curr = (start.0.clone(), start.1.clone());
-    path.insert(curr.0, curr.1);
-    let mut prev = *start.0;
-    loop {
-        // look left, right, up, down
-        let positions = vec![
-            Position {
-                x: curr.0.x - 1,
-                y: curr.0.y,
-            },
-            Position {
-                x: curr.0.x + 1,
-                y: curr.0.y,
-            },
-            Position {
-                x: curr.0.x,
-                y: curr.0.y + 1,
-            },
-            Position {
-                x: curr.0.x,
-                y: curr.0.y - 1,
-            },
-        ];
-        let next = positions
-            .iter()
-            .filter(|p| **p != prev)
-            .find(|position| match map.get(position) {
-                Some(p) => {
-                    if is_connected(curr, (**position, p.clone())) {
-                        true
+    /// Draws the maze with box-drawing characters: the main loop in double
+    /// lines, any other pipe in single lines, `●` for ground inside the loop
+    /// and `·` for ground outside it. With `ansi` the loop is also bold, junk
+    /// pipes dim and everything inside green.
+    pub fn render(&self, ansi: bool) -> String {
+        let insides = self.insides();
+        let on_loop = self.on_loop();
+        let mut out = String::new();
+        for row in 0..self.height {
+            for i in row * self.width..(row + 1) * self.width {
+                let pipe = self.tiles[i];
+                let (glyph, style) = if on_loop[i] {
+                    (pipe.glyph(true), "\x1b[1m")
+                } else if pipe == Pipe::Air {
+                    if insides[i] {
+                        ('●', "\x1b[32m")
                     } else {
-                        false
+                        ('·', "")
                     }
+                } else if insides[i] {
+                    (pipe.glyph(false), "\x1b[2;32m")
+                } else {
+                    (pipe.glyph(false), "\x1b[2m")
+                };
+                if ansi && !style.is_empty() {
+                    out.push_str(&format!("{style}{glyph}\x1b[0m"));
+                } else {
+                    out.push(glyph);
                 }
-                None => false,
-            })
-            .unwrap();
-        prev = curr.0.clone();
-        curr = (*next, *map.get(next).unwrap());
-        path.insert(curr.0, curr.1);
-
-        if Pipe::Start == curr.1 {
-            break;
+            }
+            out.push('\n');
         }
+        out
     }
+}
 
-    // OK, now we have the map with everything (original)
-    // and path with just the actual pipes.
-    // Now use the ray trace stuff I had to get from reddit to know what to do.
-    // This determines in/out of the 'polygon'
-    input
-        .lines()
-        .enumerate()
-        .map(|(y, line)| {
-            let mut is_in = false;
-            line.chars()
-                .enumerate()
-                .map(|(x, _)| {
-                    let p = Position {
-                        x: x as i32,
-                        y: y as i32,
-                    };
-                    let pipe = map.get(&p).unwrap();
-                    let is_in_path = path.contains_key(&p);
-
-                    match (is_in_path, pipe) {
-                        (true, Pipe::Vertical)
-                        | (true, Pipe::Start)  
-                            // A proper solution would determine if Start
-                            // should be included in the toggling bit or not. For my input it
-                            // should not have been included but it would depend on what pipe part
-                            // the start piece actually was.
-                            // But the tests need this to be included so include it.
-                        | (true, Pipe::DownAndRight)
-                        | (true, Pipe::DownAndLeft) => {
-                            is_in = !is_in;
-                            0
-                        }
-                        (true, _) => 0,
-                        _ => {
-                            if is_in {
-                                1
-                            } else {
-                                0
-                            }
-                        }
-                    }
-                })
-                .sum::<u32>()
-        })
-        .sum::<u32>()
-        .to_string()
+pub fn day10_parse(input: &str) {
+    std::hint::black_box(input.parse::<PipeMaze>().unwrap());
+}
+
+pub fn day10_part1(input: &str) -> String {
+    let maze = input.parse::<PipeMaze>().unwrap();
+    maze.farthest().1.to_string()
+}
+
+pub fn day10_part2(input: &str) -> String {
+    let maze = input.parse::<PipeMaze>().unwrap();
+    maze.enclosed().to_string()
 }
 
 #[cfg(test)]
@@ -375,9 +407,10 @@ mod test {
         vec![(Position {x: 1,y: 0}, UpAndLeft), (Position{x: 0,y:0}, Horizontal)],
         true
     )]
+    // J opens to the west, so the start there can connect to it.
     #[case(
         vec![(Position {x: 1,y: 0}, UpAndLeft), (Position{x: 0,y:0}, Start)],
-        false
+        true
     )]
     #[case(
         vec![(Position {x: 1,y: 0}, DownAndLeft), (Position{x: 0,y:0}, Start)],
@@ -446,7 +479,7 @@ LJ...",
 .||....||.
 .||....||.
 .|L-7F-J|.
-.|..||..|.
+.|-.||..|.
 .L--JL--J.
 ..........",
         "4"
@@ -459,7 +492,7 @@ LJ...",
 .||....||.
 .||....||.
 .|L-7F-J|.
-.|..||..|.
+.|-.||..|.
 .L--JL--J.
 ..........",
         "10"
@@ -485,7 +518,80 @@ L--J.L7...LJS7F-7L7.
 ....L---J.LJ.LJLJ...",
         "8"
     )]
+    #[case("S7\nLJ", "0")]
     fn test_day10_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(expected, day10_part2(input));
     }
+
+    #[test]
+    fn test_pipe_maze() {
+        let maze = "-L|F7
+7S-7|
+L|7||
+-L-J|
+L|-JF"
+            .parse::<PipeMaze>()
+            .unwrap();
+        assert_eq!(Pipe::DownAndRight, maze.pipe_at(maze.start()));
+        assert_eq!(8, maze.loop_length());
+        assert_eq!((Position { x: 3, y: 3 }, 4), maze.farthest());
+        assert_eq!(
+            vec![
+                Position { x: 1, y: 1 },
+                Position { x: 1, y: 3 },
+                Position { x: 3, y: 3 },
+                Position { x: 3, y: 1 },
+            ],
+            maze.vertices()
+        );
+        assert_eq!(1, maze.enclosed());
+
+        assert_eq!(
+            Err("unknown tile 'x' at (1, 0)".to_string()),
+            ".x".parse::<PipeMaze>().map(|_| ())
+        );
+        assert_eq!(
+            Err("the start connects 0 ways".to_string()),
+            ".S.".parse::<PipeMaze>().map(|_| ())
+        );
+        // The bottom middle | doesn't open west. Turning north there anyway
+        // used to send the walk round the top right forever.
+        assert_eq!(
+            Err("the loop breaks at (1, 2)".to_string()),
+            "S-7\n|||\nL|J".parse::<PipeMaze>().map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let maze = "..........
+.S------7.
+.|F----7|.
+.||....||.
+.||.-7.||.
+.|L-7F-J|.
+.|-.||..|.
+.L--JL--J.
+..........
+"
+        .parse::<PipeMaze>()
+        .unwrap();
+        assert_eq!(
+            "··········
+·╔══════╗·
+·║╔════╗║·
+·║║····║║·
+·║║·─┐·║║·
+·║╚═╗╔═╝║·
+·║─●║║●●║·
+·╚══╝╚══╝·
+··········
+",
+            maze.render(false)
+        );
+        let insides = maze.insides().iter().filter(|inside| **inside).count();
+        assert_eq!(maze.enclosed(), insides);
+        assert!(maze.render(true).contains("\x1b[2m─\x1b[0m"));
+        assert!(maze.render(true).contains("\x1b[2;32m─\x1b[0m"));
+    }
 }
@@ -1,291 +1,175 @@
-use std::collections::{HashMap, HashSet};
-
-use itertools::Itertools;
-use valence_math::IVec2;
-
-#[derive(Debug)]
-enum Item {
-    Empty,
-    Galaxy(usize), // id
+/// Galaxy positions and, for every row and column, how many empty ones
+/// come before it. That's all distances need at any expansion, so the
+/// expanded universe is never built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Universe {
+    /// `(x, y)` in reading order.
+    pub galaxies: Vec<(usize, usize)>,
+    empty_rows_before: Vec<u128>,
+    empty_cols_before: Vec<u128>,
 }
-// ^ isn't really needed. There's a good bit of simplification that can be done in the solution
-// here.
-
-// NOT used in part2
-// amount is the multiplier. so in part 1 its 2 for 2x each row.
-// this is only used in part1. There's no need for it still to exist
-// but I might come back and look at these some day (unlikely) and
-// seems worth keeping how I got here mapped out.
-fn expand_universe(input: &str, amount: u32) -> String {
-    // expand rows
-    let mut cols_with_galaxies: HashSet<usize> = HashSet::new();
 
-    let universe = input
-        .lines()
-        .flat_map(|line| {
-            if !line.contains("#") {
-                (0..amount).map(|_| line).collect::<Vec<_>>()
-            } else {
-                line.chars().enumerate().for_each(|(x, c)| {
-                    if c == '#' {
-                        cols_with_galaxies.insert(x);
-                    }
-                });
-                vec![line]
-            }
+fn empty_before(occupied: &[bool]) -> Vec<u128> {
+    occupied
+        .iter()
+        .scan(0, |empty, occupied| {
+            let before = *empty;
+            *empty += u128::from(!occupied);
+            Some(before)
         })
-        .join("\n");
+        .collect()
+}
 
-    let universe = universe
-        .lines()
-        .map(|line| {
-            line.chars()
-                .enumerate()
-                .map(|(x, c)| {
-                    if cols_with_galaxies.get(&x).is_none() {
-                        (0..amount).map(|_| c.to_string()).join("")
-                    } else {
-                        c.to_string()
-                    }
-                })
-                .join("")
-        })
-        .join("\n");
-    universe
+/// Sum of `|a - b|` over every pair, from the sorted values: the `i`th
+/// smallest is bigger than the `i` before it.
+fn pairwise_sum(mut values: Vec<u128>) -> Option<u128> {
+    values.sort_unstable();
+    let mut below = 0u128;
+    let mut total = 0u128;
+    for (i, value) in values.into_iter().enumerate() {
+        total = total.checked_add(value.checked_mul(i as u128)?.checked_sub(below)?)?;
+        below = below.checked_add(value)?;
+    }
+    Some(total)
 }
 
-fn parse(input: &String) -> HashMap<IVec2, Item> {
-    let mut galaxy_id = 0;
-    input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.chars().enumerate().map(move |(x, p)| match p {
-                '.' => (
-                    IVec2 {
-                        x: x as i32,
-                        y: y as i32,
-                    },
-                    Item::Empty,
-                ),
-                '#' => {
-                    galaxy_id += 1;
-                    (
-                        IVec2 {
-                            x: x as i32,
-                            y: y as i32,
-                        },
-                        Item::Galaxy(galaxy_id.clone()),
-                    )
-                }
-                _ => unreachable!(),
-            })
-        })
-        .collect::<HashMap<IVec2, Item>>()
+fn too_big() -> String {
+    "the distance doesn't fit in a u128".to_string()
 }
 
-pub fn find_shortest_path(
-    a: &IVec2,
-    b: &IVec2,
-    empty_cols: &Vec<usize>,
-    empty_rows: &Vec<usize>,
-    expansion: u32,
-) -> u64 {
-    if a == b {
-        return 0;
+impl Universe {
+    pub fn parse(input: &str) -> Universe {
+        let galaxies = input
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect::<Vec<_>>();
+        let width = input.lines().map(str::len).max().unwrap_or(0);
+        let mut rows = vec![false; input.lines().count()];
+        let mut cols = vec![false; width];
+        for (x, y) in &galaxies {
+            rows[*y] = true;
+            cols[*x] = true;
+        }
+        Universe {
+            galaxies,
+            empty_rows_before: empty_before(&rows),
+            empty_cols_before: empty_before(&cols),
+        }
     }
 
-    let mut moves: u64 = 0;
-    let next_x: i32;
-    let next_y: i32;
-
-    match a.x.cmp(&b.x) {
-        std::cmp::Ordering::Less => next_x = a.x + 1,
-        std::cmp::Ordering::Equal => next_x = a.x,
-        std::cmp::Ordering::Greater => next_x = a.x - 1,
-    }
-    match a.y.cmp(&b.y) {
-        std::cmp::Ordering::Less => next_y = a.y + 1,
-        std::cmp::Ordering::Equal => next_y = a.y,
-        std::cmp::Ordering::Greater => next_y = a.y - 1,
+    /// Where a galaxy ends up once each empty row and column is `expansion`
+    /// wide. Fails for an expansion of 0, which would have the universe
+    /// shrink, or if the answer doesn't fit in a `u128`.
+    pub fn expanded(&self, galaxy: usize, expansion: u128) -> Result<(u128, u128), String> {
+        if expansion == 0 {
+            return Err("empty rows and columns can't expand to nothing".to_string());
+        }
+        let (x, y) = self.galaxies[galaxy];
+        let grow = expansion - 1;
+        let expand = |at: usize, empty_before: u128| {
+            empty_before
+                .checked_mul(grow)
+                .and_then(|extra| (at as u128).checked_add(extra))
+                .ok_or(too_big())
+        };
+        Ok((
+            expand(x, self.empty_cols_before[x])?,
+            expand(y, self.empty_rows_before[y])?,
+        ))
     }
 
-    // Calculate the moves needed, and account for expansion if needed
-    if next_y != a.y {
-        moves = 1;
-        if empty_rows.contains(&(next_y as usize)) {
-            moves += (expansion as u64) - 1;
-        }
+    pub fn distance(&self, a: usize, b: usize, expansion: u128) -> Result<u128, String> {
+        let a = self.expanded(a, expansion)?;
+        let b = self.expanded(b, expansion)?;
+        a.0.abs_diff(b.0)
+            .checked_add(a.1.abs_diff(b.1))
+            .ok_or(too_big())
     }
-    if next_x != a.x {
-        moves += 1;
-        if empty_cols.contains(&(next_x as usize)) {
-            moves += (expansion as u64) - 1;
-        }
+
+    /// Sum of the distances between every pair of galaxies. x and y are
+    /// independent, so each is a sort and a running sum.
+    pub fn total_distance(&self, expansion: u128) -> Result<u128, String> {
+        let (xs, ys) = (0..self.galaxies.len())
+            .map(|galaxy| self.expanded(galaxy, expansion))
+            .collect::<Result<(Vec<_>, Vec<_>), _>>()?;
+        pairwise_sum(xs)
+            .zip(pairwise_sum(ys))
+            .and_then(|(x, y)| x.checked_add(y))
+            .ok_or(too_big())
     }
+}
 
-    return moves
-        + find_shortest_path(
-            &IVec2 {
-                x: next_x,
-                y: next_y,
-            },
-            b,
-            empty_cols,
-            empty_rows,
-            expansion,
-        );
+pub fn day11_parse(input: &str) {
+    std::hint::black_box(Universe::parse(input));
 }
 
 pub fn day11_part1(input: &str) -> String {
-    // expand the universe first
-    let universe = expand_universe(input, 2);
-    // parse id and position of each galaxy
-    let universe = parse(&universe);
-    // generate pairs of galaxies
-    let galaxies = universe
-        .iter()
-        .filter(|(_, item)| match item {
-            Item::Galaxy(_) => true,
-            _ => false,
-        })
-        .collect::<HashMap<&IVec2, &Item>>();
-    // pair up all galaxies
-    let galaxy_pairs = galaxies
-        .keys()
-        .flat_map(|k| {
-            galaxies
-                .keys()
-                .flat_map(move |k2| if k2 == k { None } else { Some((k, k2)) })
-        })
-        .collect::<Vec<_>>();
-    // find shortest path between each pair
-    (galaxy_pairs
-        .iter()
-        .map(|(a, b)| find_shortest_path(a, b, &vec![], &vec![], 2))
-        .sum::<u64>()
-        / 2) // lazy...we calculated (0,0) > (1,1) as well as (1,1)>(0,0) so half it :facepalm:
-    .to_string()
+    day11_part2_inner(input, 2)
 }
 
 pub fn day11_part2(input: &str) -> String {
     day11_part2_inner(input, 1_000_000)
 }
 
-pub fn day11_part2_inner(input: &str, expansion_amount: u32) -> String {
-    // uhhhh, don't expand anything. We're going to need to use some simple math.
-    // parse id and position of each galaxy
-    let universe = parse(&input.to_string());
-    // generate ids of rows that are empty and cols that are empty
-    let mut cols_with_galaxies: HashSet<usize> = HashSet::new();
-
-    let empty_rows = input
-        .lines()
-        .enumerate()
-        .filter_map(|(y, line)| {
-            if !line.contains("#") {
-                Some(y)
-            } else {
-                line.chars().enumerate().for_each(|(x, c)| {
-                    if c == '#' {
-                        cols_with_galaxies.insert(x);
-                    }
-                });
-                None // not empty
-            }
-        })
-        .collect::<Vec<usize>>();
-
-    let line = input.lines().next().unwrap();
-    let empty_cols = line
-        .chars()
-        .enumerate()
-        .filter_map(|(x, _)| {
-            if cols_with_galaxies.contains(&x) {
-                None
-            } else {
-                Some(x)
-            }
-        })
-        .collect::<Vec<usize>>();
-
-    // generate pairs of galaxies
-    let galaxies = universe
-        .iter()
-        .filter(|(_, item)| match item {
-            Item::Galaxy(_) => true,
-            _ => false,
-        })
-        .collect::<HashMap<&IVec2, &Item>>();
-    // pair up all galaxies
-    let galaxy_pairs = galaxies
-        .keys()
-        .flat_map(|k| {
-            galaxies
-                .keys()
-                .flat_map(move |k2| if k2 == k { None } else { Some((k, k2)) })
-        })
-        .collect::<Vec<_>>();
-    // find shortest path between each pair
-    (galaxy_pairs
-        .iter()
-        .map(|(a, b)| find_shortest_path(a, b, &empty_cols, &empty_rows, expansion_amount))
-        .sum::<u64>()
-        / 2) // lazy...we calculated (0,0) > (1,1) as well as (1,1)>(0,0) so half it :facepalm:
-    .to_string()
+pub fn day11_part2_inner(input: &str, expansion_amount: u128) -> String {
+    Universe::parse(input)
+        .total_distance(expansion_amount)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .to_string()
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use rstest::rstest;
-    use valence_math::*;
 
+    const INPUT: &str = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+
+    // Galaxies are numbered from 1 in the puzzle text.
     #[rstest]
-    #[case(
-        IVec2{x:0, y:0},
-        IVec2{x:1,y:1},
-        2
-    )]
-    #[case(
-        IVec2{x:5, y:0},
-        IVec2{x:1,y:0},
-        4
-    )]
-    #[case(
-        IVec2{x:5, y:11},
-        IVec2{x:1,y:6},
-        9
-    )]
-    fn test_day11_distances(#[case] a: IVec2, #[case] b: IVec2, #[case] expected: u64) {
-        assert_eq!(expected, find_shortest_path(&a, &b, &vec![], &vec![], 2));
+    #[case(5, 9, 9)]
+    #[case(1, 7, 15)]
+    #[case(3, 6, 17)]
+    #[case(8, 9, 5)]
+    fn test_day11_distances(#[case] a: usize, #[case] b: usize, #[case] expected: u128) {
+        let universe = Universe::parse(INPUT);
+        assert_eq!(Ok(expected), universe.distance(a - 1, b - 1, 2));
     }
 
-    #[rstest]
-    #[case(
-        "...
-..#
-.#.",
-        2,
-        4,
-        4
-    )]
-    #[case(
-        "..
-..", 10, 20, 20
-    )]
-    fn test_day11_expand(
-        #[case] input: &str,
-        #[case] expansion_amount: u32,
-        #[case] row_count: u32,
-        #[case] col_count: u32,
-    ) {
-        let expanded = expand_universe(input, expansion_amount);
-        assert_eq!(row_count, expanded.lines().count() as u32);
-        assert_eq!(
-            col_count,
-            expanded.lines().next().unwrap().chars().count() as u32
-        );
+    #[test]
+    fn test_huge_expansion() {
+        let universe = Universe::parse(INPUT);
+        let expansion = u64::MAX as u128;
+        // 4 empty rows or columns between galaxies 3 and 6, and 9 ordinary steps.
+        assert_eq!(Ok(9 + 4 * expansion), universe.distance(2, 5, expansion));
+        assert!(universe.total_distance(expansion).is_ok());
+        assert_eq!(Err(too_big()), universe.total_distance(u128::MAX));
+    }
+
+    #[test]
+    fn test_no_expansion() {
+        let universe = Universe::parse(INPUT);
+        let error = Err("empty rows and columns can't expand to nothing".to_string());
+        assert_eq!(error, universe.total_distance(0));
+        assert_eq!(error, universe.distance(2, 5, 0));
+        // 1 leaves the universe as it is: the 9 ordinary steps and the 4
+        // empty rows and columns, one step each.
+        assert_eq!(Ok(13), universe.distance(2, 5, 1));
     }
 
     #[rstest]
@@ -293,19 +177,7 @@ mod test {
         "..#
 ..#", "1"
     )]
-    #[case(
-        "...#......
-.......#..
-#.........
-..........
-......#...
-.#........
-.........#
-..........
-.......#..
-#...#.....",
-        "374"
-    )]
+    #[case(INPUT, "374")]
     fn test_day11_part1(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(expected, day11_part1(input));
     }
@@ -325,37 +197,11 @@ mod test {
         100,
         "101"
     )]
-    #[case(
-        "...#......
-.......#..
-#.........
-..........
-......#...
-.#........
-.........#
-..........
-.......#..
-#...#.....",
-        10,
-        "1030"
-    )]
-    #[case(
-        "...#......
-.......#..
-#.........
-..........
-......#...
-.#........
-.........#
-..........
-.......#..
-#...#.....",
-        100,
-        "8410"
-    )]
+    #[case(INPUT, 10, "1030")]
+    #[case(INPUT, 100, "8410")]
     fn test_day11_part2(
         #[case] input: &str,
-        #[case] expansion_amount: u32,
+        #[case] expansion_amount: u128,
         #[case] expected: &str,
     ) {
         assert_eq!(expected, day11_part2_inner(input, expansion_amount));
@@ -1,92 +1,273 @@
-use itertools::Itertools;
-use std::collections::HashMap;
+use std::{fmt, str::FromStr};
 
-// credit hyperneutrino https://www.youtube.com/watch?v=g3Ms5e7Jdqo
-// I brute forced part 1 :sweatsmile: but wouldn't have gotten part2 with that.
-fn count_springs<'a>(
-    springs: &'a str,
-    pattern: &[usize],
-    cache: &mut HashMap<(&'a str, Vec<usize>), usize>,
-) -> usize {
-    if springs.is_empty() {
-        // 1 if the pattern is also empty (this was valid)
-        return if pattern.is_empty() { 1 } else { 0 };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Unknown,
+    Filled,
+    Empty,
+}
+
+impl Cell {
+    fn from_char(c: char) -> Option<Cell> {
+        match c {
+            '?' => Some(Cell::Unknown),
+            '#' => Some(Cell::Filled),
+            '.' => Some(Cell::Empty),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = match self {
+            Cell::Unknown => '?',
+            Cell::Filled => '#',
+            Cell::Empty => '.',
+        };
+        write!(f, "{c}")
+    }
+}
+
+/// One row of springs (or one line of a nonogram) and the runs of `#` it
+/// must contain, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub cells: Vec<Cell>,
+    /// Run lengths, none of them 0.
+    pub runs: Vec<usize>,
+}
+
+impl FromStr for Row {
+    type Err = String;
+
+    // ???.### 1,1,3
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (cells, runs) = line
+            .split_once(' ')
+            .ok_or(format!("expected cells and runs in '{line}'"))?;
+        let cells = cells
+            .chars()
+            .map(|c| Cell::from_char(c).ok_or(format!("unknown cell '{c}'")))
+            .collect::<Result<_, _>>()?;
+        let runs = runs
+            .split(',')
+            .map(|run| match run.parse::<usize>() {
+                Ok(0) => Err(format!("run '{run}': runs can't be empty")),
+                Ok(run) => Ok(run),
+                Err(e) => Err(format!("run '{run}': {e}")),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Row { cells, runs })
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for cell in &self.cells {
+            write!(f, "{cell}")?;
+        }
+        let runs = self
+            .runs
+            .iter()
+            .map(|run| run.to_string())
+            .collect::<Vec<_>>();
+        write!(f, " {}", runs.join(","))
+    }
+}
+
+impl Row {
+    pub fn new(cells: Vec<Cell>, runs: Vec<usize>) -> Row {
+        Row { cells, runs }
     }
 
-    if pattern.is_empty() {
-        // 1 only if springs only has . or ? (all the ?'s would become .'s for a volid match)
-        return if springs.contains('#') { 0 } else { 1 };
+    /// `times` copies of the row joined by `?`, and the runs repeated to match.
+    pub fn unfold(&self, times: usize) -> Row {
+        let mut cells = self.cells.clone();
+        for _ in 1..times {
+            cells.push(Cell::Unknown);
+            cells.extend(&self.cells);
+        }
+        Row {
+            cells,
+            runs: self.runs.repeat(times),
+        }
     }
 
-    let key = (springs, pattern.to_vec());
-    if cache.get(&key).is_some() {
-        return *cache.get(&key).unwrap();
+    /// Whether run `run` can start at `start`: room for it, nothing empty
+    /// under it and nothing filled straight after it.
+    fn fits(&self, run: usize, start: usize) -> bool {
+        let end = start + self.runs[run];
+        end <= self.cells.len()
+            && !self.cells[start..end].contains(&Cell::Empty)
+            && self.cells.get(end) != Some(&Cell::Filled)
     }
 
-    let mut result = 0;
+    // credit hyperneutrino https://www.youtube.com/watch?v=g3Ms5e7Jdqo
+    // The same recursion as the video, filled in as a table from the back.
+    /// `ways[i][j]` is how many ways `cells[i..]` can hold `runs[j..]`.
+    fn ways(&self) -> Vec<Vec<u128>> {
+        let (len, runs) = (self.cells.len(), self.runs.len());
+        let mut ways = vec![vec![0u128; runs + 1]; len + 1];
+        ways[len][runs] = 1;
+        for i in (0..len).rev() {
+            for j in 0..=runs {
+                let mut total = 0;
+                if self.cells[i] != Cell::Filled {
+                    total += ways[i + 1][j];
+                }
+                if self.cells[i] != Cell::Empty && j < runs && self.fits(j, i) {
+                    total += ways[(i + self.runs[j] + 1).min(len)][j + 1];
+                }
+                ways[i][j] = total;
+            }
+        }
+        ways
+    }
+
+    pub fn count(&self) -> u128 {
+        self.ways()[0][0]
+    }
 
-    let first_spring = springs.chars().nth(0).unwrap();
-    match first_spring {
-        '.' | '?' => {
-            // recurse without the leading spring char, treating it as a .
-            result += count_springs(&springs[1..], pattern, cache);
+    /// The `n`th arrangement, counting from 0 with `.` before `#` at the first
+    /// cell that differs. `nth(random % count())` is a uniform sample.
+    pub fn nth(&self, n: u128) -> Option<Vec<bool>> {
+        self.nth_from(&self.ways(), n)
+    }
+
+    /// `nth` walking an already built `ways` table.
+    fn nth_from(&self, ways: &[Vec<u128>], mut n: u128) -> Option<Vec<bool>> {
+        if n >= ways[0][0] {
+            return None;
         }
-        _ => (),
-    }
-
-    match first_spring {
-        '#' | '?' => {
-            if pattern[0] <= springs.len() // only consider if the springs count can equal needed pattern amount
-                && !springs[0..pattern[0]].contains('.') // and that number of springs are operational
-                && (pattern[0] == springs.len() || springs.chars().nth(pattern[0]).unwrap() != '#')
-            // ^ and (springs can all be springs to satisfy the pattern || the spring at the end
-            // of the pattern can be broken (to satisfy the border needed)
-            {
-                let springs = springs.get(pattern[0] + 1..).or(Some("")).unwrap();
-                result += count_springs(&springs, &pattern[1..], cache);
+        let len = self.cells.len();
+        let mut filled = vec![false; len];
+        let (mut i, mut j) = (0, 0);
+        while i < len {
+            if self.cells[i] != Cell::Filled {
+                if n < ways[i + 1][j] {
+                    i += 1;
+                    continue;
+                }
+                n -= ways[i + 1][j];
             }
+            let end = i + self.runs[j];
+            filled[i..end].fill(true);
+            i = (end + 1).min(len);
+            j += 1;
+        }
+        Some(filled)
+    }
+
+    /// Every arrangement, in the order `nth` counts them.
+    pub fn arrangements(&self) -> impl Iterator<Item = Vec<bool>> + '_ {
+        let ways = self.ways();
+        (0..ways[0][0]).map_while(move |n| self.nth_from(&ways, n))
+    }
+
+    /// The cells that are the same in every arrangement, with `Unknown` for
+    /// the rest. `None` if there are no arrangements at all.
+    pub fn forced(&self) -> Option<Vec<Cell>> {
+        if self.count() == 0 {
+            return None;
         }
-        _ => (),
+        let mut trial = self.clone();
+        let forced = (0..self.cells.len())
+            .map(|i| {
+                if self.cells[i] != Cell::Unknown {
+                    return self.cells[i];
+                }
+                trial.cells[i] = Cell::Filled;
+                let can_fill = trial.count() > 0;
+                trial.cells[i] = Cell::Empty;
+                let can_empty = trial.count() > 0;
+                trial.cells[i] = Cell::Unknown;
+                match (can_fill, can_empty) {
+                    (true, false) => Cell::Filled,
+                    (false, true) => Cell::Empty,
+                    _ => Cell::Unknown,
+                }
+            })
+            .collect();
+        Some(forced)
     }
-    cache.insert(key, result);
-    result
 }
 
-pub fn day12_part1(input: &str) -> String {
-    let mut cache: HashMap<(&str, Vec<usize>), usize> = HashMap::new();
-    let mut pattern: Vec<usize> = Vec::new();
+/// A 2-D nonogram: runs for every row, top to bottom, and every column,
+/// left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Picross {
+    pub rows: Vec<Vec<usize>>,
+    pub columns: Vec<Vec<usize>>,
+}
+
+impl Picross {
+    /// Fills in forced cells row by row and column by column until nothing
+    /// changes, then guesses a cell and backtracks if that goes wrong.
+    /// Returns the first solution found.
+    pub fn solve(&self) -> Option<Vec<Vec<Cell>>> {
+        let grid = vec![vec![Cell::Unknown; self.columns.len()]; self.rows.len()];
+        self.solve_from(grid)
+    }
+
+    fn solve_from(&self, mut grid: Vec<Vec<Cell>>) -> Option<Vec<Vec<Cell>>> {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (y, runs) in self.rows.iter().enumerate() {
+                let row = Row::new(grid[y].clone(), runs.clone()).forced()?;
+                changed |= row != grid[y];
+                grid[y] = row;
+            }
+            for (x, runs) in self.columns.iter().enumerate() {
+                let column = grid.iter().map(|row| row[x]).collect();
+                let column = Row::new(column, runs.clone()).forced()?;
+                for (y, cell) in column.into_iter().enumerate() {
+                    changed |= grid[y][x] != cell;
+                    grid[y][x] = cell;
+                }
+            }
+        }
+
+        let Some((y, x)) = (0..grid.len())
+            .flat_map(|y| (0..grid[y].len()).map(move |x| (y, x)))
+            .find(|(y, x)| grid[*y][*x] == Cell::Unknown)
+        else {
+            return Some(grid);
+        };
+        [Cell::Filled, Cell::Empty].into_iter().find_map(|guess| {
+            let mut grid = grid.clone();
+            grid[y][x] = guess;
+            self.solve_from(grid)
+        })
+    }
+}
+
+fn parse(input: &str) -> Vec<Row> {
     input
         .lines()
-        .map(|line| {
-            let mut split = line.split(" ");
-            let spring_sheet = split.next().unwrap();
-            pattern = split
-                .next()
-                .unwrap()
-                .split(",")
-                .map(|p| p.parse::<usize>().unwrap())
-                .collect();
-
-            count_springs(spring_sheet, &pattern, &mut cache)
-        })
-        .sum::<usize>()
+        .map(|line| line.parse::<Row>().unwrap_or_else(|e| panic!("{e}")))
+        .collect()
+}
+
+pub fn day12_parse(input: &str) {
+    std::hint::black_box(parse(input));
+}
+
+pub fn day12_part1(input: &str) -> String {
+    parse(input)
+        .iter()
+        .map(Row::count)
+        .sum::<u128>()
         .to_string()
 }
 
 pub fn day12_part2(input: &str) -> String {
-    let input = input
-        .lines()
-        .map(|line| {
-            let sides = line.split(" ").collect::<Vec<&str>>();
-            let springs = sides.first().unwrap();
-            let count_springss = sides.last().unwrap();
-            let springs = (0..5).map(|_| springs).join("?");
-            let count_springss = (0..5).map(|_| *count_springss).join(",");
-            vec![springs, count_springss].join(" ")
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
-    day12_part1(&input)
+    parse(input)
+        .iter()
+        .map(|row| row.unfold(5).count())
+        .sum::<u128>()
+        .to_string()
 }
 
 #[cfg(test)]
@@ -121,4 +302,81 @@ mod test {
     fn test_day12_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(expected, day12_part2(input));
     }
+
+    fn row(line: &str) -> Row {
+        line.parse().unwrap()
+    }
+
+    fn render(filled: &[bool]) -> String {
+        filled.iter().map(|f| if *f { '#' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let row = row("?###???????? 3,2,1");
+        assert_eq!(10, row.count());
+        let all = row.arrangements().map(|a| render(&a)).collect::<Vec<_>>();
+        assert_eq!(10, all.len());
+        assert_eq!(".###....##.#", all[0]);
+        assert_eq!(".###.##.#...", all[9]);
+        // '#' sorts before '.', so the order is reversed as strings.
+        assert!(all.windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(None, row.nth(10));
+
+        assert_eq!(row, row.unfold(1));
+        assert_eq!(
+            "???.###????.### 1,1,3,1,1,3",
+            self::row("???.### 1,1,3").unfold(2).to_string()
+        );
+        assert_eq!(
+            Err("run '0': runs can't be empty".to_string()),
+            "??? 1,0".parse::<Row>()
+        );
+    }
+
+    #[test]
+    fn test_forced() {
+        // 8 long with a run of 6 has to cover the middle 4.
+        let forced = row("???????? 6").forced().unwrap();
+        assert_eq!(
+            "??####??",
+            forced.iter().map(|c| c.to_string()).collect::<String>()
+        );
+        let forced = row("?#?.? 3").forced().unwrap();
+        assert_eq!(
+            "###..",
+            forced.iter().map(|c| c.to_string()).collect::<String>()
+        );
+        assert_eq!(None, row("#.# 3").forced());
+    }
+
+    #[test]
+    fn test_picross() {
+        // A heart.
+        let picross = Picross {
+            rows: vec![vec![1, 1], vec![5], vec![5], vec![3], vec![1]],
+            columns: vec![vec![2], vec![4], vec![4], vec![4], vec![2]],
+        };
+        let solution = picross
+            .solve()
+            .unwrap()
+            .iter()
+            .map(|row| row.iter().map(|c| c.to_string()).collect::<String>())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![".#.#.", "#####", "#####", ".###.", "..#.."], solution);
+
+        // Needs a guess: two ways to place a diagonal.
+        let picross = Picross {
+            rows: vec![vec![1], vec![1]],
+            columns: vec![vec![1], vec![1]],
+        };
+        let solution = picross.solve().unwrap();
+        assert_eq!(vec![Cell::Filled, Cell::Empty], solution[0]);
+
+        let impossible = Picross {
+            rows: vec![vec![2]],
+            columns: vec![vec![1], vec![]],
+        };
+        assert_eq!(None, impossible.solve());
+    }
 }
@@ -1,184 +1,159 @@
-use num::Integer;
-use std::collections::HashSet;
-
 // Part 1 note: this took me a long time. Most of the time wrestling with my rust knowledge. As
 // with most of my solutions this feels overly verbose.
 
 #[derive(Debug, PartialEq, Copy, Clone)]
-enum MirrorDirection {
+pub enum MirrorDirection {
     Vertical,
     Horizontal,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-struct MirrorPoint {
-    direction: MirrorDirection,
-    count: u32,
+/// Two cells, `(x, y)`, that the mirror says should match but don't. Either
+/// one could be the smudge; `a` is the one above or left of the mirror.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Smudge {
+    pub a: (usize, usize),
+    pub b: (usize, usize),
 }
 
-fn is_horizontal_palindrome(input: &str) -> bool {
-    if input.len().is_odd() {
-        return false;
-    }
-
-    input.chars().zip(input.chars().rev()).all(|(a, b)| a == b)
+#[derive(Debug, PartialEq, Clone)]
+pub struct MirrorPoint {
+    pub direction: MirrorDirection,
+    /// Columns left of a vertical mirror, or rows above a horizontal one.
+    pub count: u32,
+    pub smudges: Vec<Smudge>,
 }
 
-fn is_vertical_palindrome(input: &str) -> bool {
-    let line_count = input.lines().count();
-    if line_count.is_odd() {
-        return false;
-    }
-
-    input.lines().zip(input.lines().rev()).all(|(a, b)| a == b)
-}
-
-// This and vertical could probably be broken out to a trait? one acting on a single &str and the
-// other acting on a Vec<&str>? 
-// wait...and I don't need to test so much as I am :facepalm:. This just needs to find the one
-// mirror point. Anyway, moving on.
-fn count_above_horizontal_mirror(input: &str, ignoring: &Option<MirrorPoint>) -> Option<MirrorPoint> {
-    let count = input.lines().count();
-    let mut set: HashSet<i32> = HashSet::new();
-    for i in (1..count).rev() {
-        if is_vertical_palindrome(&input.lines().collect::<Vec<_>>()[0..i].join("\n")) {
-            set.insert((i / 2) as i32);
+impl MirrorPoint {
+    pub fn summary(&self) -> u32 {
+        match self.direction {
+            MirrorDirection::Vertical => self.count,
+            MirrorDirection::Horizontal => self.count * 100,
         }
     }
-    for i in 0..count-1 {
-        if is_vertical_palindrome(&input.lines().collect::<Vec<_>>()[i..count].join("\n")) {
-            set.insert(((i as i32 - count as i32))/2);
-        }
-    }
-
-    if set.len() == 0 {
-        return None;
-    }
+}
 
-    let winner = set.iter().filter_map(|w| {
-        let potential = if w.signum() == 1 { *w } else { count as i32 + *w };
-        if ignoring.is_some_and(|i| i.count == (potential as u32)) {
-            None
-        } else {
-            Some(potential as u32)
-        }
-    }).collect::<HashSet<u32>>();
+/// A pattern as bitmasks, both ways round: bit `x` of `rows[y]` and bit `y`
+/// of `columns[x]` are set for a `#` at `(x, y)`. Each line spans as many
+/// words as it needs, so patterns can be any size.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Pattern {
+    rows: Vec<Vec<u64>>,
+    columns: Vec<Vec<u64>>,
+}
 
-    if winner.len() == 0 {
-        return None;
-    }
-    assert_eq!(1, winner.len());
+/// `(line, bit, mirrored line)`
+type Mismatch = (usize, usize, usize);
 
-    Some(MirrorPoint {
-        direction: MirrorDirection::Horizontal,
-        count: *winner.iter().nth(0).unwrap(),
-    })
+fn differences<'a>(a: &'a [u64], b: &'a [u64]) -> impl Iterator<Item = (usize, u64)> + 'a {
+    a.iter().zip(b).map(|(a, b)| a ^ b).enumerate()
 }
 
-fn count_left_of_vertical_mirror(input: &str, ignoring: &Option<MirrorPoint>) -> Option<MirrorPoint> {
-    let col_count = input.lines().nth(0).unwrap().len();
-
-    // look at each line and store the x position for each possible mirror:
-    // look for palindrome from left edge, coming from the right.
-    let winner = input
-        .lines()
-        .map(|line| {
-            (1..col_count)
-                .filter_map(|x| {
-                    if is_horizontal_palindrome(line[0..x].as_ref()) {
-                        Some((x / 2) as i32)
-                    } else if is_horizontal_palindrome(line[x..col_count].as_ref()) {
-                        let position = (x as i32 - col_count as i32)/2;
-                        Some(position)
-                    } else {
-                        None
-                    }
-                })
-                .collect::<HashSet<i32>>()
+/// Every mirror position between `lines` with exactly `k` mismatched bits
+/// across it, and where each mismatch is.
+fn mirrors(lines: &[Vec<u64>], k: u32) -> Vec<(usize, Vec<Mismatch>)> {
+    (1..lines.len())
+        .filter_map(|axis| {
+            let pairs = (0..axis).rev().zip(axis..lines.len());
+            let count = pairs
+                .clone()
+                .flat_map(|(a, b)| differences(&lines[a], &lines[b]))
+                .map(|(_, diff)| diff.count_ones())
+                .sum::<u32>();
+            (count == k).then(|| {
+                let mismatches = pairs
+                    .flat_map(|(a, b)| {
+                        differences(&lines[a], &lines[b]).flat_map(move |(word, diff)| {
+                            (0..u64::BITS as usize)
+                                .filter(move |bit| diff & (1 << bit) != 0)
+                                .map(move |bit| (a, word * u64::BITS as usize + bit, b))
+                        })
+                    })
+                    .collect();
+                (axis, mismatches)
+            })
         })
-        .reduce(|acc, item| &acc & &item);
-
-    let winner = winner.unwrap();
-
-    if winner.len() == 0 {
-        return None;
-    }
+        .collect()
+}
 
-    let winner = winner.iter().filter_map(|w| {
-        let potential = if w.signum() == 1 { *w } else { col_count as i32 + *w };
-        if ignoring.is_some_and(|i| i.count == (potential as u32)) {
-            None
-        } else {
-            Some(potential as u32)
+impl Pattern {
+    pub fn parse(input: &str) -> Pattern {
+        let words = |bits: usize| bits.div_ceil(u64::BITS as usize);
+        let width = input.lines().map(str::len).max().unwrap_or(0);
+        let height = input.lines().count();
+        let mut rows = vec![vec![0; words(width)]; height];
+        let mut columns = vec![vec![0; words(height)]; width];
+        for (y, line) in input.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c == '#' {
+                    rows[y][x / 64] |= 1 << (x % 64);
+                    columns[x][y / 64] |= 1 << (y % 64);
+                }
+            }
         }
-    }).collect::<HashSet<u32>>();
+        Pattern { rows, columns }
+    }
 
-    if winner.len() == 0 {
-        return None;
+    /// Every mirror with exactly `k` smudges: vertical ones left to right,
+    /// then horizontal ones top to bottom. `k = 0` is a clean reflection.
+    pub fn reflections(&self, k: u32) -> Vec<MirrorPoint> {
+        let vertical = mirrors(&self.columns, k)
+            .into_iter()
+            .map(|(axis, mismatches)| MirrorPoint {
+                direction: MirrorDirection::Vertical,
+                count: axis as u32,
+                smudges: mismatches
+                    .into_iter()
+                    .map(|(a, y, b)| Smudge {
+                        a: (a, y),
+                        b: (b, y),
+                    })
+                    .collect(),
+            });
+        let horizontal = mirrors(&self.rows, k)
+            .into_iter()
+            .map(|(axis, mismatches)| MirrorPoint {
+                direction: MirrorDirection::Horizontal,
+                count: axis as u32,
+                smudges: mismatches
+                    .into_iter()
+                    .map(|(a, x, b)| Smudge {
+                        a: (x, a),
+                        b: (x, b),
+                    })
+                    .collect(),
+            });
+        vertical.chain(horizontal).collect()
     }
-    assert_eq!(1, winner.len());
+}
 
-    Some(MirrorPoint {
-        direction: MirrorDirection::Vertical,
-        count: *winner.iter().nth(0).unwrap(),
-    })
+fn parse(input: &str) -> Vec<Pattern> {
+    input.split("\n\n").map(Pattern::parse).collect()
 }
 
-pub fn day13_part1(input: &str) -> String {
-    input
-        .split("\n\n")
-        .map(|map| {
-            if let Some(left) = count_left_of_vertical_mirror(map, &None) {
-                return left.count;
-            } else if let Some(above) = count_above_horizontal_mirror(map, &None) {
-                return above.count * 100;
-            } else {
-                unreachable!("should always have a mirror point?");
-            }
+fn summarize(input: &str, smudges: u32) -> String {
+    parse(input)
+        .iter()
+        .map(|pattern| {
+            pattern
+                .reflections(smudges)
+                .first()
+                .expect("should always have a mirror point?")
+                .summary()
         })
         .sum::<u32>()
         .to_string()
 }
 
-pub fn day13_part2(input: &str) -> String {
-    // brute force hunt :grimacing:
-    // this actually isn't slow[0] so :shrugging:
-    //
-    // [0] compared to yesterday and other days :sweatsmile:
-    input
-        .split("\n\n")
-        .map(|map| {
-            let original_left = count_left_of_vertical_mirror(map, &None);
-            let original_above = count_above_horizontal_mirror(map, &None);
+pub fn day13_parse(input: &str) {
+    std::hint::black_box(parse(input));
+}
 
-            let mut answer: Option<u32> = None;
-            let mut idx: usize = 0;
-            while answer.is_none() {
-                let replacement;
-                match map.get(idx..idx+1).unwrap() {
-                    "." => replacement = "#",
-                    "#" => replacement = ".",
-                    _ => {idx+=1; continue;},
-                }
-                let mut test = map.to_string();
-                test.replace_range(idx..idx+1, replacement);
-                assert_ne!(map, test);
-                let left = count_left_of_vertical_mirror(&test, &original_left);
-                if left.is_some() && left != original_left {
-                    answer = Some(left.unwrap().count);
-                    break;
-                }
-                let above = count_above_horizontal_mirror(&test, &original_above);
-                if above.is_some() {
-                    answer = Some(above.unwrap().count * 100);
-                    break;
-                }
-                idx+=1;
-            }
-            answer.unwrap()
-        })
-        .sum::<u32>()
-        .to_string()
+pub fn day13_part1(input: &str) -> String {
+    summarize(input, 0)
+}
+
+pub fn day13_part2(input: &str) -> String {
+    summarize(input, 1)
 }
 
 #[cfg(test)]
@@ -186,36 +161,19 @@ mod test {
     use super::*;
     use rstest::rstest;
 
-    #[rstest]
-    #[case("##..##", true)]
-    #[case("##...##", false)]
-    #[case("##.#..#.##", true)]
-    #[case(".##..##.", true)]
-    fn test_day13_is_horizontal_palindrome(#[case] input: &str, #[case] expected: bool) {
-        assert_eq!(expected, is_horizontal_palindrome(input));
-    }
-
-    #[rstest]
-    #[case("##..##
-##..##", true)]
-    #[case("##...##
-##.#.##", false)]
-    #[case("##.#
-##.#
-##.#", false)]
-    #[case(".#.
-#.#
-#.#
-.#.", true)]
-    fn test_day13_is_vertical_palindrome(#[case] input: &str, #[case] expected: bool) {
-        assert_eq!(expected, is_vertical_palindrome(input));
+    fn first(input: &str, direction: MirrorDirection) -> Option<u32> {
+        Pattern::parse(input)
+            .reflections(0)
+            .into_iter()
+            .find(|mirror| mirror.direction == direction)
+            .map(|mirror| mirror.count)
     }
 
     #[rstest]
     #[case(
         "##.#.##..##.
 ##.......#..",
-       Some(MirrorPoint{direction: MirrorDirection::Vertical, count: 1})
+        Some(1)
     )]
     #[case(
         "...#........#..
@@ -235,7 +193,7 @@ mod test {
 #####......###.
 ..#..######..#.
 ..#..#....#..#.",
-       Some(MirrorPoint{direction: MirrorDirection::Vertical, count: 1})
+        Some(1)
     )]
     #[case(
         "#.##..##.
@@ -245,7 +203,7 @@ mod test {
 ..#.##.#.
 ..##..##.
 #.#.##.#.",
-       Some(MirrorPoint{direction: MirrorDirection::Vertical, count: 5}) 
+        Some(5)
     )]
     #[case(
         "#...##..#
@@ -254,37 +212,41 @@ mod test {
 #####.##.
 #####.##.
 ..##..###
-#....#..#", None
+#....#..#",
+        None
     )]
     // #[case("##..#", 2)]
     // #[case("####..#", 2)]
-    fn test_day13_count_left_of_vertical_mirror(#[case] input: &str, #[case] expected: Option<MirrorPoint>) {
-        assert_eq!(expected, count_left_of_vertical_mirror(input, &None));
+    fn test_day13_count_left_of_vertical_mirror(
+        #[case] input: &str,
+        #[case] expected: Option<u32>,
+    ) {
+        assert_eq!(expected, first(input, MirrorDirection::Vertical));
     }
 
-#[rstest]
-#[case(
-"#....#..#
+    #[rstest]
+    #[case(
+        "#....#..#
 ..##..###
 #####.##.
 #####.##.
 ..##..###
 #....#..#
 #...##..#",
-        Some(MirrorPoint{direction: MirrorDirection::Horizontal, count: 3})
+        Some(3)
     )]
-#[case(
-"#...##..#
+    #[case(
+        "#...##..#
 #....#..#
 ..##..###
 #####.##.
 #####.##.
 ..##..###
 #....#..#",
-        Some(MirrorPoint{direction: MirrorDirection::Horizontal, count: 4})
+        Some(4)
     )]
-#[case(
-    "#..##..
+    #[case(
+        "#..##..
 #.#.##.
 #.####.
 .####..
@@ -301,10 +263,10 @@ mod test {
 ###.###
 .####..
 .#####.",
-        Some(MirrorPoint{direction: MirrorDirection::Horizontal, count: 14})
+        Some(14)
     )]
-    fn test_day13_count_above(#[case] input: &str, #[case] expected: Option<MirrorPoint>) {
-        assert_eq!(expected, count_above_horizontal_mirror(input, &None));
+    fn test_day13_count_above(#[case] input: &str, #[case] expected: Option<u32>) {
+        assert_eq!(expected, first(input, MirrorDirection::Horizontal));
     }
 
     #[rstest]
@@ -351,4 +313,83 @@ mod test {
     fn test_day13_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(expected, day13_part2(input));
     }
+
+    #[test]
+    fn test_smudges() {
+        let pattern = Pattern::parse(
+            "#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.",
+        );
+        let smudged = pattern.reflections(1);
+        assert_eq!(1, smudged.len());
+        assert_eq!(MirrorDirection::Horizontal, smudged[0].direction);
+        assert_eq!(3, smudged[0].count);
+        assert_eq!(
+            vec![Smudge {
+                a: (0, 0),
+                b: (0, 5)
+            }],
+            smudged[0].smudges
+        );
+        assert_eq!(5, pattern.reflections(0)[0].summary());
+        // Two smudges would make a vertical mirror after the first column.
+        assert_eq!(
+            vec![MirrorPoint {
+                direction: MirrorDirection::Vertical,
+                count: 1,
+                smudges: vec![
+                    Smudge {
+                        a: (0, 0),
+                        b: (1, 0)
+                    },
+                    Smudge {
+                        a: (0, 6),
+                        b: (1, 6)
+                    },
+                ],
+            }],
+            pattern.reflections(2)
+        );
+        assert_eq!(Vec::<MirrorPoint>::new(), pattern.reflections(3));
+    }
+
+    #[test]
+    fn test_patterns_bigger_than_a_word() {
+        // 70 wide and 80 tall: 66 columns of noise, then `.##.`, so
+        // the only mirror sits between the two rocks.
+        let line = |y: usize| {
+            (0..66)
+                .map(|x| {
+                    if ((x * 31 + y * 17) ^ (x * y)).is_multiple_of(3) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .chain(".##.".chars())
+                .collect::<String>()
+        };
+        let input = (0..80).map(line).collect::<Vec<_>>().join("\n");
+        let mirrors = Pattern::parse(&input).reflections(0);
+        assert_eq!(1, mirrors.len());
+        assert_eq!(MirrorDirection::Vertical, mirrors[0].direction);
+        assert_eq!(68, mirrors[0].count);
+
+        // Put a rock in the bottom right corner.
+        let smudged = input[..input.len() - 1].to_string() + "#";
+        let mirror = &Pattern::parse(&smudged).reflections(1)[0];
+        assert_eq!(68, mirror.count);
+        assert_eq!(
+            vec![Smudge {
+                a: (66, 79),
+                b: (69, 79)
+            }],
+            mirror.smudges
+        );
+    }
 }
@@ -1,207 +1,212 @@
-use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
-use std::hash::Hasher;
+use std::{collections::HashMap, fmt, str::FromStr};
 
-use glam::IVec2;
-use itertools::Itertools;
+/// The platform as bitmasks, one `u128` per row with bit `x` for column `x`,
+/// so it can be up to 128 wide and tall. The cube rocks never move, so the
+/// runs of open space between them are worked out once for rows and columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    width: usize,
+    height: usize,
+    cubes: Vec<u128>,
+    rounded: Vec<u128>,
+    row_gaps: Vec<Vec<u128>>,
+    column_gaps: Vec<Vec<u128>>,
+}
 
-#[derive(Debug, PartialEq)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     North,
     West,
     South,
     East,
 }
 
-#[derive(Debug)]
-enum Rock {
-    Movable,
-    Stable,
+fn ones(count: u32) -> u128 {
+    u128::MAX.checked_shr(128 - count).unwrap_or(0)
 }
 
-fn count_rocks_that_would_fall_to_top(map: &str) -> u32 {
-    // walk each column and count the rocks, O, until we hit a #, then move on.
-    let col_count = map.lines().nth(0).unwrap().chars().count();
-    let height = map.lines().count();
+/// Contiguous runs of clear bits in the low `len` bits of `blocked`.
+fn gaps(blocked: u128, len: usize) -> Vec<u128> {
+    let mut gaps = vec![];
+    let mut open = !blocked & ones(len as u32);
+    while open != 0 {
+        let start = open.trailing_zeros();
+        let run = (open >> start).trailing_ones();
+        let gap = ones(run) << start;
+        gaps.push(gap);
+        open &= !gap;
+    }
+    gaps
+}
 
-    (0..col_count)
-        .map(|x| {
-            let mut empty_space = 0;
-            map.lines()
-                .enumerate()
-                .map(|(y, line)| match &line[x..=x] {
-                    "O" => height - (y - empty_space),
-                    "." => {
-                        empty_space += 1;
-                        0
-                    }
-                    "#" => {
-                        empty_space = 0;
-                        0
-                    }
-                    _ => unreachable!("inconceivable!"),
-                })
-                .sum::<usize>()
-        })
-        .sum::<usize>() as u32
+/// Swaps rows for columns: bit `x` of `lines[y]` becomes bit `y` of line `x`.
+fn transpose(lines: &[u128], len: usize) -> Vec<u128> {
+    let mut transposed = vec![0; len];
+    for (y, line) in lines.iter().enumerate() {
+        let mut bits = *line;
+        while bits != 0 {
+            let x = bits.trailing_zeros();
+            transposed[x as usize] |= 1 << y;
+            bits &= bits - 1;
+        }
+    }
+    transposed
 }
 
-pub fn day14_part1(input: &str) -> String {
-    count_rocks_that_would_fall_to_top(input).to_string()
+/// Slides the rocks in every gap to its low end, or its high end.
+fn slide(lines: &mut [u128], gaps: &[Vec<u128>], to_low_end: bool) {
+    for (line, gaps) in lines.iter_mut().zip(gaps) {
+        let mut slid = 0;
+        for gap in gaps {
+            let rocks = (*line & gap).count_ones();
+            slid |= if to_low_end {
+                ones(rocks) << gap.trailing_zeros()
+            } else {
+                ones(rocks) << (128 - gap.leading_zeros() - rocks)
+            };
+        }
+        *line = slid;
+    }
 }
 
-fn shift_rocks(max_position: IVec2, map: &mut HashMap<IVec2, Rock>, direction: Direction) {
-    let direction_ivec = match direction {
-        Direction::North => IVec2 { x: 0, y: -1 },
-        Direction::West => IVec2 { x: -1, y: 0 },
-        Direction::South => IVec2 { x: 0, y: 1 },
-        Direction::East => IVec2 { x: 1, y: 0 },
-    };
+impl FromStr for Platform {
+    type Err = String;
 
-    for y in 0..=max_position.y {
-        for x in 0..=max_position.x {
-            // Should we be moving backwards?
-            let (x, y) = match direction {
-                Direction::South => (x, max_position.y - y),
-                Direction::East => (max_position.x - x, y),
-                _ => (x, y),
-            };
-            // does a movable rock live here?
-            let mut position = IVec2 { x, y };
-            if let Some(Rock::Movable) = map.get(&position) {
-                // Now move this to the position it would lie in.
-                loop {
-                    let next_position = position + direction_ivec;
-                    if next_position.x < 0
-                        || next_position.x > max_position.x
-                        || next_position.y < 0
-                        || next_position.y > max_position.y
-                    {
-                        // off the map, break
-                        break;
-                    }
-                    if let Some(_) = map.get(&next_position) {
-                        break;
-                    } else {
-                        // this rock can move to this spot, take it.
-                        map.remove(&position);
-                        map.insert(next_position, Rock::Movable);
-                        position = next_position;
-                    }
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let height = input.lines().count();
+        let width = input.lines().map(str::len).max().unwrap_or(0);
+        if width > 128 || height > 128 {
+            return Err(format!("{width}x{height} is bigger than 128x128"));
+        }
+        let mut cubes = vec![0; height];
+        let mut rounded = vec![0; height];
+        for (y, line) in input.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    'O' => rounded[y] |= 1 << x,
+                    '#' => cubes[y] |= 1 << x,
+                    '.' => (),
+                    _ => return Err(format!("unknown rock '{c}' at ({x}, {y})")),
                 }
             }
         }
+        let row_gaps = cubes.iter().map(|row| gaps(*row, width)).collect();
+        let column_gaps = transpose(&cubes, width)
+            .iter()
+            .map(|column| gaps(*column, height))
+            .collect();
+        Ok(Platform {
+            width,
+            height,
+            cubes,
+            rounded,
+            row_gaps,
+            column_gaps,
+        })
     }
 }
 
-// used when debugging.
-#[allow(dead_code)]
-fn rocks_as_str(max_position: IVec2, map: &HashMap<IVec2, Rock>) -> String {
-    let mut result = "".to_string();
-    for y in 0..=max_position.y {
-        for x in 0..=max_position.x {
-            result.push(match map.get(&IVec2 { x, y }) {
-                Some(Rock::Movable) => 'O',
-                Some(Rock::Stable) => '#',
-                None => '.',
-            });
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let c = if self.rounded[y] & 1 << x != 0 {
+                    'O'
+                } else if self.cubes[y] & 1 << x != 0 {
+                    '#'
+                } else {
+                    '.'
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
         }
-        result.push('\n');
+        Ok(())
     }
-    result
 }
 
-pub fn day14_part2(input: &str) -> String {
-    day14_part2_inner(input, 1_000_000_000)
-}
+impl Platform {
+    /// Every rounded rock rolls as far as it can, one pass over each row or
+    /// column.
+    pub fn tilt(&mut self, direction: Direction) {
+        match direction {
+            Direction::West | Direction::East => slide(
+                &mut self.rounded,
+                &self.row_gaps,
+                direction == Direction::West,
+            ),
+            Direction::North | Direction::South => {
+                let mut columns = transpose(&self.rounded, self.width);
+                slide(
+                    &mut columns,
+                    &self.column_gaps,
+                    direction == Direction::North,
+                );
+                self.rounded = transpose(&columns, self.height);
+            }
+        }
+    }
 
-fn rotate_rocks(max_position: IVec2, map: &mut HashMap<IVec2, Rock>) {
-    shift_rocks(max_position, map, Direction::North);
-    shift_rocks(max_position, map, Direction::West);
-    shift_rocks(max_position, map, Direction::South);
-    shift_rocks(max_position, map, Direction::East);
-}
+    /// North, west, south and east.
+    pub fn spin_cycle(&mut self) {
+        for direction in [
+            Direction::North,
+            Direction::West,
+            Direction::South,
+            Direction::East,
+        ] {
+            self.tilt(direction);
+        }
+    }
 
-pub fn day14_part2_inner(input: &str, runs: u32) -> String {
-    // parse the input in to a hashmap of position > rock type, ignore air.
-    let mut map = input
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.chars()
-                .enumerate()
-                .filter_map(|(x, c)| {
-                    let x = x as i32;
-                    let y = y as i32;
-                    match c {
-                        'O' => Some((IVec2 { x, y }, Rock::Movable)),
-                        '#' => Some((IVec2 { x, y }, Rock::Stable)),
-                        '.' => None,
-                        _ => {
-                            dbg!(c);
-                            unreachable!()
-                        }
-                    }
-                })
-                .collect::<HashMap<IVec2, Rock>>()
-        })
-        .collect::<HashMap<IVec2, Rock>>();
+    /// Runs `cycles` spin cycles. The rocks fall into a loop quickly, so once
+    /// a state repeats the rest is skipped with the loop's period.
+    pub fn spin(&mut self, cycles: u64) {
+        let mut seen = HashMap::new();
+        for cycle in 0..cycles {
+            if let Some(previous) = seen.insert(self.rounded.clone(), cycle) {
+                let remaining = (cycles - cycle) % (cycle - previous);
+                for _ in 0..remaining {
+                    self.spin_cycle();
+                }
+                return;
+            }
+            self.spin_cycle();
+        }
+    }
 
-    let max_position = IVec2 {
-        x: input.lines().nth(0).unwrap().chars().count() as i32 - 1,
-        y: input.lines().count() as i32 - 1,
-    };
+    /// Each rounded rock counts for how many rows it is from the south edge.
+    pub fn north_load(&self) -> usize {
+        self.rounded
+            .iter()
+            .enumerate()
+            .map(|(y, row)| row.count_ones() as usize * (self.height - y))
+            .sum()
+    }
+}
 
-    // 1 cache to know when we should start the loop check cache.
-    // when this cache stops growing we start putting elements in to the loop cache. When it stops
-    // growing we know the # of elements in our loop.
-    let mut cache: HashSet<u64> = HashSet::new();
-    let mut loop_cache: HashSet<u64> = HashSet::new();
+fn parse(input: &str) -> Platform {
+    input.parse().unwrap_or_else(|e| panic!("{e}"))
+}
 
-    for i in 0..runs {
-        rotate_rocks(max_position, &mut map);
+pub fn day14_parse(input: &str) {
+    std::hint::black_box(parse(input));
+}
 
-        // reduce the map state in to a unique key to put in a set to identify when we're looping.
-        // use the hash of a string as an easy way to make sure its unique
-        let v = (0..=max_position.y)
-            .map(|y| {
-                (0..=max_position.x).fold("".to_string(), |acc, x| {
-                    if map.get(&IVec2 { x, y }).is_some() {
-                        format!("{acc}x{x}x{y}").to_string()
-                    } else {
-                        acc
-                    }
-                })
-            })
-            .join(".");
-        let mut hasher = DefaultHasher::new();
-        hasher.write(v.as_bytes());
-        let v = hasher.finish();
+pub fn day14_part1(input: &str) -> String {
+    let mut platform = parse(input);
+    platform.tilt(Direction::North);
+    platform.north_load().to_string()
+}
 
-        if cache.get(&v).is_some() {
-            if loop_cache.get(&v).is_some() {
-                let remainder = ((runs - (i + 1)) as usize % loop_cache.len()) as i32;
-                for _ in 0..remainder {
-                    rotate_rocks(max_position, &mut map);
-                }
-                let score = map
-                    .iter()
-                    .filter_map(|(position, rock)| match rock {
-                        Rock::Movable => Some(1 + max_position.y - position.y),
-                        Rock::Stable => None,
-                    })
-                    .sum::<i32>();
-                return score.to_string();
-            } else {
-                loop_cache.insert(v);
-            }
-        } else {
-            // we just added a new entry to the cache, reset the loop cache
-            loop_cache = HashSet::new();
-        }
-        cache.insert(v);
-    }
+pub fn day14_part2(input: &str) -> String {
+    day14_part2_inner(input, 1_000_000_000)
+}
 
-    unreachable!("looped forever? unpossible");
+pub fn day14_part2_inner(input: &str, runs: u64) -> String {
+    let mut platform = parse(input);
+    platform.spin(runs);
+    platform.north_load().to_string()
 }
 
 #[cfg(test)]
@@ -255,7 +260,53 @@ O.#..O.#.#
         1_000_000_000,
         "64"
     )]
-    fn test_day14_part2(#[case] input: &str, #[case] runs: u32, #[case] expected: &str) {
+    fn test_day14_part2(#[case] input: &str, #[case] runs: u64, #[case] expected: &str) {
         assert_eq!(day14_part2_inner(input, runs), expected);
     }
+
+    const INPUT: &str = "O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....";
+
+    #[test]
+    fn test_spin_cycle() {
+        let mut platform = parse(INPUT);
+        platform.spin_cycle();
+        assert_eq!(
+            ".....#....
+....#...O#
+...OO##...
+.OO#......
+.....OOO#.
+.O#...O#.#
+....O#....
+......OOOO
+#...O###..
+#..OO#....
+",
+            platform.to_string()
+        );
+        let mut spun = parse(INPUT);
+        spun.spin(1);
+        assert_eq!(platform, spun);
+    }
+
+    #[test]
+    fn test_north_load() {
+        // Scored as loaded, nothing rolls.
+        assert_eq!(3, parse("O.\n.O").north_load());
+        assert_eq!(0, parse("..\n..").north_load());
+        assert!(".x".parse::<Platform>().is_err());
+
+        let mut wide = parse(&format!("{}\n{}", ".".repeat(128), "O".repeat(128)));
+        wide.tilt(Direction::North);
+        assert_eq!(256, wide.north_load());
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use std::fmt;
 
 struct Hasher {
     value: u32,
@@ -13,7 +13,7 @@ impl Hasher {
         let x = c as u32;
         self.value += x;
         self.value *= 17;
-        self.value = self.value % 256;
+        self.value %= 256;
     }
 
     fn hash_str(&mut self, str: &str) {
@@ -23,95 +23,168 @@ impl Hasher {
     }
 }
 
-pub fn day15_part1(input: &str) -> String {
-    input
-        .replace("\n", "")
-        .split(",")
-        .map(|i| {
-            let mut hasher = Hasher { value: 0 };
-            for c in i.chars() {
-                hasher.hash(c);
+/// The HASH of `s`, which is also the box it goes in.
+pub fn holiday_hash(s: &str) -> u8 {
+    let mut hasher = Hasher { value: 0 };
+    hasher.hash_str(s);
+    hasher.value as u8
+}
+
+/// The Holiday ASCII String Helper Manual Arrangement Procedure: 256 boxes
+/// picked by `holiday_hash`, each keeping its entries in the order they
+/// were first inserted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LensMap<K, V> {
+    boxes: Vec<Vec<(K, V)>>,
+}
+
+impl<K: AsRef<str>, V> Default for LensMap<K, V> {
+    fn default() -> Self {
+        LensMap {
+            boxes: (0..256).map(|_| Vec::new()).collect(),
+        }
+    }
+}
+
+impl<K: AsRef<str>, V> LensMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn slots(&self, key: &str) -> &Vec<(K, V)> {
+        &self.boxes[holiday_hash(key) as usize]
+    }
+
+    fn slots_mut(&mut self, key: &str) -> &mut Vec<(K, V)> {
+        &mut self.boxes[holiday_hash(key) as usize]
+    }
+
+    /// Replaces the value in place if the key is already there, returning the
+    /// old value, otherwise adds it to the back of its box.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let slots = self.slots_mut(key.as_ref());
+        match slots.iter_mut().find(|(k, _)| k.as_ref() == key.as_ref()) {
+            Some((_, existing)) => Some(std::mem::replace(existing, value)),
+            None => {
+                slots.push((key, value));
+                None
             }
-            hasher.value
+        }
+    }
+
+    /// Takes the key out, moving everything behind it in its box forward.
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let slots = self.slots_mut(key);
+        let position = slots.iter().position(|(k, _)| k.as_ref() == key)?;
+        Some(slots.remove(position).1)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.slots(key)
+            .iter()
+            .find(|(k, _)| k.as_ref() == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn len(&self) -> usize {
+        self.boxes.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.iter().all(Vec::is_empty)
+    }
+
+    /// `(box, slot, key, value)` box by box, front to back within each box.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &K, &V)> {
+        self.boxes.iter().enumerate().flat_map(|(b, slots)| {
+            slots
+                .iter()
+                .enumerate()
+                .map(move |(slot, (k, v))| (b, slot, k, v))
         })
-        .sum::<u32>()
-        .to_string()
+    }
+}
+
+impl<K: AsRef<str>, V: Copy + Into<usize>> LensMap<K, V> {
+    // One plus the box number of the lens in question.
+    // The slot number of the lens within the box: 1 for the first lens, 2 for the second lens, and so on.
+    // The focal length of the lens.
+    pub fn focusing_power(&self) -> usize {
+        self.iter()
+            .map(|(b, slot, _, focal_length)| (b + 1) * (slot + 1) * (*focal_length).into())
+            .sum()
+    }
 }
 
-#[derive(Debug)]
+/// The boxes with anything in them, the way the puzzle prints them:
+/// `Box 3: [ot 7] [ab 5] [pc 6]`.
+impl<K: fmt::Display, V: fmt::Display> fmt::Display for LensMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (b, slots) in self.boxes.iter().enumerate() {
+            if slots.is_empty() {
+                continue;
+            }
+            write!(f, "Box {b}:")?;
+            for (k, v) in slots {
+                write!(f, " [{k} {v}]")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 enum Command<'a> {
     Set((&'a str, u8)),
     Remove(&'a str),
 }
 
-pub fn day15_part2(input: &str) -> String {
-    // Hash of box id to sorted VecDeque of tuples with the label + the lens focal length
-    let mut boxes: HashMap<u8, VecDeque<(&str, u8)>> = HashMap::new();
-    let input = input.replace("\n", "");
-    input.split(",").for_each(|cmd| {
-        let command = if cmd.contains("=") {
-            let mut cmd = cmd.split("=");
-            Command::Set((
-                cmd.nth(0).unwrap(),
-                cmd.nth(0).unwrap().parse::<u8>().unwrap(),
-            ))
-        } else if cmd.contains("-") {
-            Command::Remove(cmd.split("-").nth(0).unwrap())
+impl<'a> Command<'a> {
+    fn parse(step: &'a str) -> Command<'a> {
+        if let Some((label, focal_length)) = step.split_once('=') {
+            Command::Set((label, focal_length.parse::<u8>().unwrap()))
+        } else if let Some(label) = step.strip_suffix('-') {
+            Command::Remove(label)
         } else {
-            unreachable!();
-        };
-
-        let mut hasher = Hasher { value: 0 };
-        match command {
-            Command::Set((a, _)) => hasher.hash_str(a),
-            Command::Remove(a) => hasher.hash_str(a),
-        }
-
-        let box_id = hasher.value as u8;
-        if !boxes.contains_key(&box_id) {
-            boxes.insert(box_id, VecDeque::new());
+            unreachable!("unknown step {step}");
         }
-        let box_contents = boxes.get_mut(&box_id).unwrap();
-        let existing = box_contents.iter().enumerate().find(|(_, v)| {
-            v.0 == match command {
-                Command::Set(cmd) => cmd.0,
-                Command::Remove(cmd) => cmd,
-            }
-        });
+    }
 
-        match command {
+    fn apply(&self, lenses: &mut LensMap<&'a str, u8>) {
+        match *self {
             Command::Set((label, focal_length)) => {
-                if existing.is_some() {
-                    let position = existing.unwrap().0;
-                    box_contents.remove(position);
-                    box_contents.insert(position, (label, focal_length));
-                } else {
-                    box_contents.push_back((label, focal_length));
-                }
+                lenses.insert(label, focal_length);
             }
-            Command::Remove(_) => {
-                if existing.is_some() {
-                    box_contents.remove(existing.unwrap().0);
-                }
+            Command::Remove(label) => {
+                lenses.remove(label);
             }
         }
-    });
+    }
+}
 
-    // One plus the box number of the lens in question.
-    // The slot number of the lens within the box: 1 for the first lens, 2 for the second lens, and so on.
-    // The focal length of the lens.
+fn steps(input: &str) -> impl Iterator<Item = &str> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|step| !step.is_empty())
+}
 
-    boxes
-        .iter()
-        .flat_map(|(box_id, cabinet)| {
-            cabinet.iter().enumerate().map(|(slot, slot_content)| {
-                (*box_id as usize + 1) * (slot as usize + 1) * slot_content.1 as usize
-            })
-        })
-        .sum::<usize>()
+pub fn day15_part1(input: &str) -> String {
+    steps(input)
+        .map(|step| holiday_hash(step) as u32)
+        .sum::<u32>()
         .to_string()
 }
 
+pub fn day15_part2(input: &str) -> String {
+    let mut lenses = LensMap::new();
+    for step in steps(input) {
+        Command::parse(step).apply(&mut lenses);
+    }
+    lenses.focusing_power().to_string()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -129,4 +202,43 @@ mod test {
     fn test_day15_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(day15_part2(input), expected);
     }
+
+    #[test]
+    fn test_lens_map_steps() {
+        let mut lenses = LensMap::new();
+        let dumps = steps("rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7")
+            .map(|step| {
+                Command::parse(step).apply(&mut lenses);
+                lenses.to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!("Box 0: [rn 1]\n", dumps[0]);
+        assert_eq!("Box 0: [rn 1] [cm 2]\nBox 1: [qp 3]\n", dumps[3]);
+        assert_eq!(
+            "Box 0: [rn 1] [cm 2]\nBox 3: [ot 7] [ab 5] [pc 6]\n",
+            dumps[10]
+        );
+        assert_eq!(145, lenses.focusing_power());
+        assert_eq!(5, lenses.len());
+    }
+
+    #[test]
+    fn test_lens_map() {
+        let mut map = LensMap::new();
+        assert!(map.is_empty());
+        assert_eq!(None, map.insert("rn".to_string(), "first"));
+        assert_eq!(None, map.insert("cm".to_string(), "second"));
+        // Replacing keeps its place in the box.
+        assert_eq!(Some("first"), map.insert("rn".to_string(), "again"));
+        assert_eq!(
+            vec![(0, 0, "rn", "again"), (0, 1, "cm", "second")],
+            map.iter()
+                .map(|(b, slot, k, v)| (b, slot, k.as_str(), *v))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(&"second"), map.get("cm"));
+        assert_eq!(Some("again"), map.remove("rn"));
+        assert_eq!(None, map.remove("rn"));
+        assert_eq!(1, map.len());
+    }
 }
@@ -1,22 +1,32 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::Display,
-};
+use std::{collections::HashMap, fmt::Display, thread};
 
 use glam::IVec2;
 
 #[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
-enum Direction {
+pub enum Direction {
     North,
     South,
     East,
     West,
 }
 
+impl Direction {
+    fn step(self) -> IVec2 {
+        match self {
+            North => IVec2 { x: 0, y: -1 },
+            South => IVec2 { x: 0, y: 1 },
+            East => IVec2 { x: 1, y: 0 },
+            West => IVec2 { x: -1, y: 0 },
+        }
+    }
+}
+
+/// A beam at `position`, heading `direction`. For an entry point the
+/// position is just outside the contraption.
 #[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
-struct Beam {
-    position: IVec2,
-    direction: Direction,
+pub struct Beam {
+    pub position: IVec2,
+    pub direction: Direction,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -27,16 +37,6 @@ enum Piece {
     DownwardMirror,     // \
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct Cave {
-    beams: Vec<Beam>,
-    map: HashMap<IVec2, Piece>,
-    bounds: IVec2,
-    energized: HashSet<IVec2>,
-    moves_without_energizing: u32,
-    cache: HashMap<Vec<Beam>, u32>,
-}
-
 impl Display for Piece {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -52,31 +52,57 @@ impl Display for Piece {
     }
 }
 
-impl Display for Cave {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for y in 0..self.bounds.y {
-            for x in 0..self.bounds.x {
-                let position = IVec2 { x, y };
-                if self.energized.contains(&position) {
-                    let _ = write!(f, "#");
-                    continue;
-                }
-                let _ = match self.map.get(&position) {
-                    None => write!(f, "."),
-                    Some(piece) => write!(f, "{}", piece),
-                };
-            }
-            let _ = write!(f, "\n");
+use Direction::*;
+use Piece::*;
+
+/// One bit per tile, row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Energized(Vec<u64>);
+
+impl Energized {
+    fn new(tiles: usize) -> Energized {
+        Energized(vec![0; tiles.div_ceil(64)])
+    }
+
+    fn insert(&mut self, tile: usize) {
+        self.0[tile / 64] |= 1 << (tile % 64);
+    }
+
+    fn union(&mut self, other: &Energized) {
+        for (a, b) in self.0.iter_mut().zip(&other.0) {
+            *a |= b;
         }
-        write!(f, "")
+    }
+
+    pub fn contains(&self, tile: usize) -> bool {
+        self.0[tile / 64] & (1 << (tile % 64)) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
     }
 }
 
-use Direction::*;
-use Piece::*;
+/// The tiles a beam lights up until it reaches a splitter side-on (or leaves),
+/// and the beams that splitter sends out.
+#[derive(Debug, Clone)]
+struct Segment {
+    tiles: Vec<usize>,
+    splits: Vec<Beam>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cave {
+    map: HashMap<IVec2, Piece>,
+    bounds: IVec2,
+}
 
 fn build_cave(input: &str) -> Cave {
-    let max_x = input.lines().nth(0).unwrap().chars().count() as i32;
+    let max_x = input.lines().next().unwrap().chars().count() as i32;
     let max_y = input.lines().count() as i32;
 
     let map = input
@@ -91,189 +117,301 @@ fn build_cave(input: &str) -> Cave {
                     '\\' => Some(DownwardMirror),
                     _ => None,
                 };
-                if let Some(piece) = piece {
-                    Some((
+                piece.map(|piece| {
+                    (
                         IVec2 {
                             x: x as i32,
                             y: y as i32,
                         },
                         piece,
-                    ))
-                } else {
-                    None
-                }
+                    )
+                })
             })
         })
         .collect::<HashMap<IVec2, Piece>>();
 
-    let mut beams = Vec::new();
-    beams.push(Beam {
-        direction: East,
-        position: IVec2 { x: -1, y: 0 },
-    });
-
     Cave {
         map,
-        beams,
         bounds: IVec2 { x: max_x, y: max_y },
-        energized: HashSet::new(),
-        moves_without_energizing: 0,
-        cache: HashMap::new(),
     }
 }
 
 impl Cave {
-    fn advance_light(&mut self) -> bool {
-        let originally_energized_count = self.energized.len();
-        let mut new_beams = Vec::new();
-        self.beams.iter().for_each(|beam| {
-            let current_position = beam.position;
-            let next_position = current_position
-                + match beam.direction {
-                    North => IVec2 { x: 0, y: -1 },
-                    South => IVec2 { x: 0, y: 1 },
-                    East => IVec2 { x: 1, y: 0 },
-                    West => IVec2 { x: -1, y: 0 },
-                };
+    fn tiles(&self) -> usize {
+        (self.bounds.x * self.bounds.y) as usize
+    }
 
-            // are we off the board?
-            if next_position.x < 0
-                || next_position.y < 0
-                || next_position.y >= self.bounds.y
-                || next_position.x >= self.bounds.x
-            {
-                return;
-            }
+    fn tile(&self, position: IVec2) -> Option<usize> {
+        let inside =
+            (0..self.bounds.x).contains(&position.x) && (0..self.bounds.y).contains(&position.y);
+        inside.then(|| (position.y * self.bounds.x + position.x) as usize)
+    }
 
-            // Figure out what the next position's piece does to us.
-            match self.map.get(&next_position) {
-                Some(VerticalSplitter) => match beam.direction {
-                    North | South => {
-                        new_beams.push(Beam {
-                            position: next_position,
-                            direction: beam.direction,
-                        });
-                    }
-                    East | West => {
-                        new_beams.push(Beam {
-                            position: next_position,
-                            direction: North,
-                        });
-                        new_beams.push(Beam {
-                            position: next_position,
-                            direction: South,
-                        });
-                    }
-                },
-                Some(HorizontalSplitter) => match beam.direction {
-                    East | West => {
-                        new_beams.push(Beam {
-                            position: next_position,
-                            direction: beam.direction,
-                        });
-                    }
-                    North | South => {
-                        new_beams.push(Beam {
-                            position: next_position,
-                            direction: West,
-                        });
-                        new_beams.push(Beam {
-                            position: next_position,
-                            direction: East,
-                        });
-                    }
-                },
-                Some(UpwardMirror) => {
-                    let next_direction = match beam.direction {
-                        North => East,
-                        South => West,
-                        East => North,
-                        West => South,
-                    };
-                    new_beams.push(Beam {
-                        position: next_position,
-                        direction: next_direction,
-                    });
+    /// Every beam that can come in from outside.
+    pub fn entries(&self) -> Vec<Beam> {
+        let (w, h) = (self.bounds.x, self.bounds.y);
+        let beam = |x, y, direction| Beam {
+            position: IVec2 { x, y },
+            direction,
+        };
+        (0..h)
+            .flat_map(|y| [beam(-1, y, East), beam(w, y, West)])
+            .chain((0..w).flat_map(|x| [beam(x, -1, South), beam(x, h, North)]))
+            .collect()
+    }
+
+    fn segment(&self, start: Beam) -> Segment {
+        let mut tiles = vec![];
+        let mut beam = start;
+        // Mirrors and pass-throughs can be walked backwards, so a beam that
+        // never meets a splitter side-on either leaves or comes back round
+        // to where it started.
+        loop {
+            beam.position += beam.direction.step();
+            let Some(tile) = self.tile(beam.position) else {
+                return Segment {
+                    tiles,
+                    splits: vec![],
+                };
+            };
+            if beam == start {
+                return Segment {
+                    tiles,
+                    splits: vec![],
+                };
+            }
+            tiles.push(tile);
+            let split = |a, b| {
+                [a, b].map(|direction| Beam {
+                    position: beam.position,
+                    direction,
+                })
+            };
+            beam.direction = match (self.map.get(&beam.position), beam.direction) {
+                (Some(VerticalSplitter), East | West) => {
+                    let splits = split(North, South).to_vec();
+                    return Segment { tiles, splits };
                 }
-                Some(DownwardMirror) => {
-                    let next_direction = match beam.direction {
-                        North => West,
-                        South => East,
-                        East => South,
-                        West => North,
-                    };
-                    new_beams.push(Beam {
-                        position: next_position,
-                        direction: next_direction,
-                    });
+                (Some(HorizontalSplitter), North | South) => {
+                    let splits = split(East, West).to_vec();
+                    return Segment { tiles, splits };
                 }
-                None => new_beams.push(Beam {
-                    position: next_position,
-                    direction: beam.direction,
-                }),
-            }
-
-            self.energized.insert(next_position);
-        });
-
-        self.beams = new_beams;
-        if originally_energized_count == self.energized.len() {
-            self.moves_without_energizing += 1;
-        } else {
-            self.moves_without_energizing = 0;
+                (Some(UpwardMirror), North) => East,
+                (Some(UpwardMirror), South) => West,
+                (Some(UpwardMirror), East) => North,
+                (Some(UpwardMirror), West) => South,
+                (Some(DownwardMirror), North) => West,
+                (Some(DownwardMirror), South) => East,
+                (Some(DownwardMirror), East) => South,
+                (Some(DownwardMirror), West) => North,
+                (_, direction) => direction,
+            };
         }
+    }
 
-        // / 2 to speed it up a tiny bit and this is enough to cover things...:science:
-        self.beams.len() > 0 && (self.moves_without_energizing as i32) < (self.bounds.x / 2)
+    /// Builds a `BeamEngine` with everything each beam leaving a splitter
+    /// lights up already worked out.
+    pub fn engine(&self) -> BeamEngine<'_> {
+        let mut engine = BeamEngine {
+            cave: self,
+            index: HashMap::new(),
+            segments: vec![],
+            energized: vec![],
+        };
+        for (position, piece) in &self.map {
+            let directions = match piece {
+                VerticalSplitter => [North, South],
+                HorizontalSplitter => [East, West],
+                _ => continue,
+            };
+            for direction in directions {
+                let beam = Beam {
+                    position: *position,
+                    direction,
+                };
+                engine.index.insert(beam, engine.segments.len());
+                engine.segments.push(self.segment(beam));
+            }
+        }
+        engine.energized = engine.condense();
+        engine
     }
 }
 
-pub fn day16_part1(input: &str) -> String {
-    let mut cave = build_cave(input);
-
-    while cave.advance_light() {}
-
-    cave.energized.len().to_string()
+/// The contraption cut into segments that start at a splitter. Splitters
+/// feed each other in loops, so segments are grouped into strongly connected
+/// components; each group lights the same tiles.
+pub struct BeamEngine<'a> {
+    cave: &'a Cave,
+    index: HashMap<Beam, usize>,
+    segments: Vec<Segment>,
+    energized: Vec<Energized>,
 }
 
-pub fn day16_part2(input: &str) -> String {
-    // brute force? then memoize?
-    let mut cave = build_cave(input);
-    let mut winning = 0;
-
-    for y in 0..cave.bounds.y {
-        for x in vec![-1, cave.bounds.x] {
-            cave.beams = vec![];
-            cave.energized = HashSet::new();
+struct Tarjan<'a> {
+    successors: &'a [Vec<usize>],
+    order: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    next: usize,
+    components: Vec<Vec<usize>>,
+}
 
-            cave.beams.push(Beam {
-                position: IVec2 { x, y },
-                direction: if x < 0 { East } else { West },
-            });
+impl Tarjan<'_> {
+    fn visit(&mut self, node: usize) {
+        self.order[node] = Some(self.next);
+        self.low[node] = self.next;
+        self.next += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+        for &next in &self.successors[node] {
+            match self.order[next] {
+                None => {
+                    self.visit(next);
+                    self.low[node] = self.low[node].min(self.low[next]);
+                }
+                Some(order) if self.on_stack[next] => {
+                    self.low[node] = self.low[node].min(order);
+                }
+                _ => (),
+            }
+        }
+        if Some(self.low[node]) == self.order[node] {
+            let mut component = vec![];
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
 
-            while cave.advance_light() {}
+impl BeamEngine<'_> {
+    /// What each segment lights up, following every split. Tarjan finishes
+    /// components after everything they lead to, so each can just take the
+    /// union of its own tiles and what it feeds.
+    fn condense(&self) -> Vec<Energized> {
+        let successors = self
+            .segments
+            .iter()
+            .map(|segment| segment.splits.iter().map(|beam| self.index[beam]).collect())
+            .collect::<Vec<Vec<_>>>();
+        let mut tarjan = Tarjan {
+            successors: &successors,
+            order: vec![None; successors.len()],
+            low: vec![0; successors.len()],
+            stack: vec![],
+            on_stack: vec![false; successors.len()],
+            next: 0,
+            components: vec![],
+        };
+        for node in 0..successors.len() {
+            if tarjan.order[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
 
-            winning = winning.max(cave.energized.len());
+        let mut energized: Vec<Option<Energized>> = vec![None; successors.len()];
+        for component in &tarjan.components {
+            let mut lit = Energized::new(self.cave.tiles());
+            for &member in component {
+                for &tile in &self.segments[member].tiles {
+                    lit.insert(tile);
+                }
+                for next in &successors[member] {
+                    if let Some(next) = &energized[*next] {
+                        lit.union(next);
+                    }
+                }
+            }
+            for &member in component {
+                energized[member] = Some(lit.clone());
+            }
         }
+        energized.into_iter().map(Option::unwrap).collect()
     }
 
-    for x in 0..cave.bounds.x {
-        for y in vec![-1, cave.bounds.y] {
-            cave.beams = vec![];
-            cave.energized = HashSet::new();
-
-            cave.beams.push(Beam {
-                position: IVec2 { x, y },
-                direction: if y < 0 { South } else { North },
-            });
+    /// Everything lit by a beam coming in at `entry`.
+    pub fn energized(&self, entry: Beam) -> Energized {
+        let segment = self.cave.segment(entry);
+        let mut lit = Energized::new(self.cave.tiles());
+        for tile in segment.tiles {
+            lit.insert(tile);
+        }
+        for split in &segment.splits {
+            lit.union(&self.energized[self.index[split]]);
+        }
+        lit
+    }
 
-            while cave.advance_light() {}
+    /// The entry point that lights up the most tiles, and how many. Spread
+    /// over `threads` threads.
+    pub fn best_entry(&self, threads: usize) -> Option<(Beam, usize)> {
+        let entries = self.cave.entries();
+        let chunk = entries.len().div_ceil(threads.max(1)).max(1);
+        thread::scope(|scope| {
+            let workers = entries
+                .chunks(chunk)
+                .map(|entries| {
+                    scope.spawn(move || {
+                        entries
+                            .iter()
+                            .map(|entry| (*entry, self.energized(*entry).len()))
+                            .max_by_key(|(_, lit)| *lit)
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .filter_map(|worker| worker.join().unwrap())
+                .max_by_key(|(_, lit)| *lit)
+        })
+    }
 
-            winning = winning.max(cave.energized.len());
+    /// The contraption with every lit tile drawn as `#`.
+    pub fn render(&self, energized: &Energized) -> String {
+        let mut out = String::new();
+        for y in 0..self.cave.bounds.y {
+            for x in 0..self.cave.bounds.x {
+                let position = IVec2 { x, y };
+                if energized.contains(self.cave.tile(position).unwrap()) {
+                    out.push('#');
+                } else {
+                    match self.cave.map.get(&position) {
+                        Some(piece) => out.push_str(&piece.to_string()),
+                        None => out.push('.'),
+                    }
+                }
+            }
+            out.push('\n');
         }
+        out
     }
+}
+
+pub fn day16_parse(input: &str) {
+    std::hint::black_box(build_cave(input));
+}
+
+pub fn day16_part1(input: &str) -> String {
+    let cave = build_cave(input);
+    let entry = Beam {
+        direction: East,
+        position: IVec2 { x: -1, y: 0 },
+    };
+    cave.engine().energized(entry).len().to_string()
+}
 
-    winning.to_string()
+pub fn day16_part2(input: &str) -> String {
+    let cave = build_cave(input);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let (_, lit) = cave.engine().best_entry(threads).unwrap();
+    lit.to_string()
 }
 
 #[cfg(test)]
@@ -316,4 +454,58 @@ mod test {
     fn test_day16_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(day16_part2(input), expected);
     }
+
+    const INPUT: &str = r#".|...\....
+|.-.\.....
+.....|-...
+........|.
+..........
+.........\
+..../.\\..
+.-.-/..|..
+.|....-|.\
+..//.|...."#;
+
+    #[test]
+    fn test_best_entry() {
+        let cave = build_cave(INPUT);
+        let engine = cave.engine();
+        let best = Beam {
+            position: IVec2 { x: 3, y: -1 },
+            direction: South,
+        };
+        assert_eq!(Some((best, 51)), engine.best_entry(1));
+        assert_eq!(Some((best, 51)), engine.best_entry(4));
+        assert_eq!(
+            r#".#####....
+|#-#\#....
+.#.#.#####
+.#.#.##.|.
+.#.#.##...
+.#.#.##..\
+.#.#####..
+########..
+.#######.\
+.#//.#.#..
+"#,
+            engine.render(&engine.energized(best))
+        );
+    }
+
+    #[test]
+    fn test_loops_through_splitters() {
+        // The beams leaving the - go round the ring, back through the - end
+        // on, and would carry on forever.
+        let cave = build_cave(
+            r#".|.
+/-\
+\-/"#,
+        );
+        let engine = cave.engine();
+        let entry = Beam {
+            position: IVec2 { x: 1, y: -1 },
+            direction: South,
+        };
+        assert_eq!(7, engine.energized(entry).len());
+    }
 }
@@ -1,44 +1,73 @@
 use glam::IVec2;
 use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::{BinaryHeap, HashMap};
 
-#[derive(Debug, Copy, Clone)]
+const DIRECTIONS: [IVec2; 4] = [
+    IVec2 { x: 0, y: 1 },
+    IVec2 { x: 1, y: 0 },
+    IVec2 { x: 0, y: -1 },
+    IVec2 { x: -1, y: 0 },
+];
+
+/// Where a crucible is, which way it last moved ((0, 0) before it has
+/// moved) and how many blocks it has gone that way.
+type State = (IVec2, IVec2, u8);
+
+/// A heap entry: a state (numbered by `Graph::state_index`), the heat lost
+/// getting there and the state it came from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 struct Edge {
-    to: IVec2,
-    direction: IVec2, // as (0,1), (1,0), (-1, 0), or (0, -1)
-    direction_count: u8,
+    // Field order matters: the heap orders by weight first.
     weight: u32,
+    state: usize,
+    from: Option<usize>,
 }
 
-impl PartialEq for Edge {
-    fn eq(&self, other: &Self) -> bool {
-        self.weight == other.weight
-            && self.to == other.to
-            && self.direction == other.direction
-            && self.direction_count == other.direction_count
-    }
+/// How a crucible may move: it has to go at least `min_run` blocks in a
+/// straight line before it turns (or stops at `end`) and at most `max_run`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub min_run: u8,
+    pub max_run: u8,
+    /// Whether it may turn straight back the way it came.
+    pub can_reverse: bool,
+    pub start: IVec2,
+    pub end: IVec2,
 }
 
-impl Eq for Edge {}
+impl Rules {
+    /// Top left to bottom right, turning after at most 3 blocks.
+    pub fn crucible(graph: &Graph) -> Rules {
+        Rules {
+            min_run: 1,
+            max_run: 3,
+            can_reverse: false,
+            start: IVec2 { x: 0, y: 0 },
+            end: graph.bottom_right(),
+        }
+    }
 
-impl PartialOrd for Edge {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.weight.partial_cmp(&other.weight)
+    /// Between 4 and 10 blocks before each turn, and before stopping.
+    pub fn ultra_crucible(graph: &Graph) -> Rules {
+        Rules {
+            min_run: 4,
+            max_run: 10,
+            ..Rules::crucible(graph)
+        }
     }
 }
 
-impl Ord for Edge {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other)
-            .expect("partial cmp should always return a value")
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub heat_loss: u32,
+    /// Every block visited, `start` and `end` included.
+    pub blocks: Vec<IVec2>,
 }
 
 #[derive(Debug)]
-struct Graph {
-    nodes: HashMap<IVec2, u32>, // current_cost, this_node, prev_node
-    seen_positions: HashSet<(IVec2, u8, IVec2)>, // position, direction count, direction. Intentionally doesn't include the cost.
-    heap: BinaryHeap<Reverse<Edge>>,
+pub struct Graph {
+    nodes: HashMap<IVec2, u32>, // position, heat loss
+    bounds: IVec2,
 }
 
 fn build_graph(input: &str) -> Graph {
@@ -51,188 +80,171 @@ fn build_graph(input: &str) -> Graph {
                     x: x as i32,
                     y: y as i32,
                 };
-                (
-                    position,
-                    c.to_digit(10).expect("only digit chars expected") as u32,
-                )
+                (position, c.to_digit(10).expect("only digit chars expected"))
             })
         })
         .collect::<HashMap<IVec2, u32>>();
 
     Graph {
         nodes,
-        seen_positions: HashSet::new(),
-        heap: BinaryHeap::new(),
+        bounds: IVec2 {
+            x: input.lines().next().unwrap().chars().count() as i32,
+            y: input.lines().count() as i32,
+        },
     }
 }
 
-pub fn day17_part1(input: &str) -> String {
-    let mut graph = build_graph(input);
-    let max_x = (input.lines().nth(0).unwrap().chars().count() as i32) - 1;
-    let max_y = (input.lines().count() as i32) - 1;
-    let end = IVec2 { x: max_x, y: max_y };
-    let beginning = IVec2 { x: 0, y: 0 };
-    graph.heap.push(Reverse(Edge {
-        to: beginning,
-        direction: beginning,
-        direction_count: 0,
-        weight: 0,
-    }));
-
-    while graph.heap.len() > 0 {
-        let next_edge = graph.heap.pop().expect("should have had an entry").0;
+impl Graph {
+    pub fn bottom_right(&self) -> IVec2 {
+        self.bounds - IVec2 { x: 1, y: 1 }
+    }
 
-        if next_edge.to == end {
-            return next_edge.weight.to_string();
-        }
+    /// Numbers every state densely: block, then direction (4 before it has
+    /// moved), then run length.
+    fn state_index(&self, (position, direction, count): State, rules: &Rules) -> usize {
+        let block = (position.y * self.bounds.x + position.x) as usize;
+        let direction = DIRECTIONS
+            .iter()
+            .position(|d| *d == direction)
+            .unwrap_or(DIRECTIONS.len());
+        (block * (DIRECTIONS.len() + 1) + direction) * (rules.max_run as usize + 1) + count as usize
+    }
 
-        if graph
-            .seen_positions
-            .get(&(next_edge.to, next_edge.direction_count, next_edge.direction))
-            .is_some()
-        {
-            continue;
-        }
+    fn state_at(&self, index: usize, rules: &Rules) -> State {
+        let runs = rules.max_run as usize + 1;
+        let count = (index % runs) as u8;
+        let direction = DIRECTIONS
+            .get(index / runs % (DIRECTIONS.len() + 1))
+            .copied()
+            .unwrap_or(IVec2::ZERO);
+        let block = (index / runs / (DIRECTIONS.len() + 1)) as i32;
+        let position = IVec2 {
+            x: block % self.bounds.x,
+            y: block / self.bounds.x,
+        };
+        (position, direction, count)
+    }
 
-        graph
-            .seen_positions
-            .insert((next_edge.to, next_edge.direction_count, next_edge.direction));
+    /// Dijkstra over (block, direction, run length). `None` if the rules
+    /// never let the crucible stop at the end.
+    pub fn solve(&self, rules: &Rules) -> Option<Route> {
+        let beginning = IVec2::ZERO;
+        let states = (self.bounds.x * self.bounds.y) as usize
+            * (DIRECTIONS.len() + 1)
+            * (rules.max_run as usize + 1);
+        // Filled in as each state is settled, so only its best way in counts.
+        let mut settled = vec![false; states];
+        let mut came_from = vec![None; states];
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse(Edge {
+            weight: 0,
+            state: self.state_index((rules.start, beginning, 0), rules),
+            from: None,
+        }));
 
-        if next_edge.direction != beginning && next_edge.direction_count < 3 {
-            // push continuing to go straight on to the queue
-            let next_position = next_edge.to + next_edge.direction;
-            match graph.nodes.get(&next_position) {
-                Some(next_node) => {
-                    graph.heap.push(Reverse(Edge {
-                        to: next_position,
-                        direction: next_edge.direction,
-                        direction_count: next_edge.direction_count + 1,
-                        weight: next_edge.weight + next_node,
-                    }));
-                }
-                None => (),
+        while let Some(Reverse(edge)) = heap.pop() {
+            if settled[edge.state] {
+                continue;
             }
-        }
+            settled[edge.state] = true;
+            came_from[edge.state] = edge.from;
 
-        for direction in vec![
-            IVec2 { x: 0, y: 1 },
-            IVec2 { x: 1, y: 0 },
-            IVec2 { x: 0, y: -1 },
-            IVec2 { x: -1, y: 0 },
-        ] {
-            // Can't go backwards and can't go forwards anymore
-            if direction == next_edge.direction
-                || direction
-                    == (IVec2 {
-                        x: -(next_edge.direction.x),
-                        y: -(next_edge.direction.y),
-                    })
-            {
-                continue;
+            let (position, heading, run) = self.state_at(edge.state, rules);
+            let moving = heading != beginning;
+            if position == rules.end && (!moving || run >= rules.min_run) {
+                return Some(self.route(edge.weight, edge.state, &came_from, rules));
             }
-            let next_position = next_edge.to + direction;
-            match graph.nodes.get(&next_position) {
-                Some(next_node) => {
-                    graph.heap.push(Reverse(Edge {
-                        to: next_position,
-                        direction,
-                        direction_count: 1,
-                        weight: next_edge.weight + next_node,
+
+            for direction in DIRECTIONS {
+                let straight = direction == heading;
+                let reverse = direction == -heading;
+                let allowed = if !moving {
+                    true
+                } else if straight {
+                    run < rules.max_run
+                } else {
+                    run >= rules.min_run && (!reverse || rules.can_reverse)
+                };
+                if !allowed {
+                    continue;
+                }
+                let next_position = position + direction;
+                if let Some(next_node) = self.nodes.get(&next_position) {
+                    let next_run = if straight { run + 1 } else { 1 };
+                    heap.push(Reverse(Edge {
+                        weight: edge.weight + next_node,
+                        state: self.state_index((next_position, direction, next_run), rules),
+                        from: Some(edge.state),
                     }));
                 }
-                None => (),
             }
         }
+        None
     }
 
-    unreachable!("should have hit the end before draining the priority queue!")
-}
-
-pub fn day17_part2(input: &str) -> String {
-    let mut graph = build_graph(input);
-    let max_x = (input.lines().nth(0).unwrap().chars().count() as i32) - 1;
-    let max_y = (input.lines().count() as i32) - 1;
-    let end = IVec2 { x: max_x, y: max_y };
-    let beginning = IVec2 { x: 0, y: 0 };
-    graph.heap.push(Reverse(Edge {
-        to: beginning,
-        direction: beginning,
-        direction_count: 0,
-        weight: 0,
-    }));
-
-    while graph.heap.len() > 0 {
-        let next_edge = graph.heap.pop().expect("should have had an entry").0;
-
-        if next_edge.to == end && next_edge.direction_count >= 4 {
-            return next_edge.weight.to_string();
+    fn route(
+        &self,
+        heat_loss: u32,
+        mut state: usize,
+        came_from: &[Option<usize>],
+        rules: &Rules,
+    ) -> Route {
+        let mut blocks = vec![self.state_at(state, rules).0];
+        while let Some(previous) = came_from[state] {
+            blocks.push(self.state_at(previous, rules).0);
+            state = previous;
         }
+        blocks.reverse();
+        Route { heat_loss, blocks }
+    }
 
-        if graph
-            .seen_positions
-            .get(&(next_edge.to, next_edge.direction_count, next_edge.direction))
-            .is_some()
-        {
-            continue;
+    /// The map with the route drawn over it the way the puzzle does, an
+    /// arrow on each block showing which way the crucible entered it.
+    pub fn render(&self, route: &Route) -> String {
+        let mut arrows = HashMap::new();
+        for pair in route.blocks.windows(2) {
+            let arrow = match pair[1] - pair[0] {
+                IVec2 { x: 1, y: 0 } => '>',
+                IVec2 { x: -1, y: 0 } => '<',
+                IVec2 { x: 0, y: 1 } => 'v',
+                _ => '^',
+            };
+            arrows.insert(pair[1], arrow);
         }
-
-        graph
-            .seen_positions
-            .insert((next_edge.to, next_edge.direction_count, next_edge.direction));
-
-        if next_edge.direction != beginning && next_edge.direction_count < 10 {
-            // push continuing to go straight on to the queue
-            let next_position = next_edge.to + next_edge.direction;
-            match graph.nodes.get(&next_position) {
-                Some(next_node) => {
-                    graph.heap.push(Reverse(Edge {
-                        to: next_position,
-                        direction: next_edge.direction,
-                        direction_count: next_edge.direction_count + 1,
-                        weight: next_edge.weight + next_node,
-                    }));
+        let mut out = String::new();
+        for y in 0..self.bounds.y {
+            for x in 0..self.bounds.x {
+                let position = IVec2 { x, y };
+                match arrows.get(&position) {
+                    Some(arrow) => out.push(*arrow),
+                    None => out.push_str(&self.nodes[&position].to_string()),
                 }
-                None => (),
             }
+            out.push('\n');
         }
+        out
+    }
+}
 
-        // The ultra has to move in 4 places first.
-        if next_edge.direction != beginning && next_edge.direction_count < 4 {
-            continue;
-        }
+fn heat_loss(input: &str, rules: fn(&Graph) -> Rules) -> String {
+    let graph = build_graph(input);
+    graph
+        .solve(&rules(&graph))
+        .expect("should have hit the end before draining the priority queue!")
+        .heat_loss
+        .to_string()
+}
 
-        for direction in vec![
-            IVec2 { x: 0, y: 1 },
-            IVec2 { x: 1, y: 0 },
-            IVec2 { x: 0, y: -1 },
-            IVec2 { x: -1, y: 0 },
-        ] {
-            // Can't go backwards and can't go forwards anymore
-            if direction == next_edge.direction
-                || direction
-                    == (IVec2 {
-                        x: -(next_edge.direction.x),
-                        y: -(next_edge.direction.y),
-                    })
-            {
-                continue;
-            }
-            let next_position = next_edge.to + direction;
-            match graph.nodes.get(&next_position) {
-                Some(next_node) => {
-                    graph.heap.push(Reverse(Edge {
-                        to: next_position,
-                        direction,
-                        direction_count: 1,
-                        weight: next_edge.weight + next_node,
-                    }));
-                }
-                None => (),
-            }
-        }
-    }
+pub fn day17_parse(input: &str) {
+    std::hint::black_box(build_graph(input));
+}
 
-    unreachable!("should have hit the end before draining the priority queue!")
+pub fn day17_part1(input: &str) -> String {
+    heat_loss(input, Rules::crucible)
+}
+
+pub fn day17_part2(input: &str) -> String {
+    heat_loss(input, Rules::ultra_crucible)
 }
 
 #[cfg(test)]
@@ -281,4 +293,56 @@ mod test {
     fn test_day17_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(day17_part2(input), expected);
     }
+
+    #[test]
+    fn test_route() {
+        let graph = build_graph(
+            "111111111111
+999999999991
+999999999991
+999999999991
+999999999991",
+        );
+        let route = graph.solve(&Rules::ultra_crucible(&graph)).unwrap();
+        assert_eq!(71, route.heat_loss);
+        let heat = route.blocks[1..]
+            .iter()
+            .map(|block| graph.nodes[block])
+            .sum::<u32>();
+        assert_eq!(route.heat_loss, heat);
+        assert_eq!(
+            "1>>>>>>>1111
+9999999v9991
+9999999v9991
+9999999v9991
+9999999v>>>>
+",
+            graph.render(&route)
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        let graph = build_graph("1111");
+        let mut rules = Rules {
+            min_run: 2,
+            max_run: 3,
+            can_reverse: false,
+            start: IVec2 { x: 0, y: 0 },
+            end: IVec2 { x: 1, y: 0 },
+        };
+        // It can't stop one block in, and has nowhere to turn.
+        assert_eq!(None, graph.solve(&rules));
+        // Out to the end of the row and two blocks back.
+        rules.can_reverse = true;
+        let route = graph.solve(&rules).unwrap();
+        assert_eq!(5, route.heat_loss);
+        assert_eq!(
+            vec![0, 1, 2, 3, 2, 1],
+            route.blocks.iter().map(|block| block.x).collect::<Vec<_>>()
+        );
+
+        rules.end = rules.start;
+        assert_eq!(0, graph.solve(&rules).unwrap().heat_loss);
+    }
 }
@@ -1,42 +1,51 @@
-use std::collections::HashSet;
-
-use glam::{I64Vec2, IVec2};
-use itertools::Itertools;
+use glam::I64Vec2;
 use nom::{
     bytes::complete::tag,
-    character::complete::{self, alpha1, alphanumeric1, newline, space1},
-    multi::separated_list1,
+    character::complete::{self, alpha1, alphanumeric1, space1},
     sequence::{delimited, terminated, tuple},
     IResult, Parser,
 };
 
-// 1. parse the input
-// 2. create a vec of points that are the edges
-// 3. use the logic from the previous day where we tested if points were in a polygon. Walk the
-//    entire map and count the other points that are in the polygon.
-// 4. done
-//
-// I'm going to parse and store the color information assuming it'll be needed in part 2 but it
-// doesn't affect anything for part 1.
-#[derive(Debug, Clone, Copy)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     North,
     East,
     South,
     West,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Instruction<'a> {
-    direction: Direction,
-    distance: i64,
-    color: &'a str,
+impl Direction {
+    fn step(self) -> I64Vec2 {
+        match self {
+            North => I64Vec2 { x: 0, y: -1 },
+            East => I64Vec2 { x: 1, y: 0 },
+            South => I64Vec2 { x: 0, y: 1 },
+            West => I64Vec2 { x: -1, y: 0 },
+        }
+    }
+}
+
+/// Which half of a line of the dig plan to follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// `R 6`
+    Plain,
+    /// The "colour": five hex digits of distance then a direction digit,
+    /// 0 to 3 for R, D, L and U.
+    Hex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub direction: Direction,
+    pub distance: i64,
 }
 
 use Direction::*;
 
-fn parse_instruction(input: &str) -> IResult<&str, Instruction> {
-    let (input, (direction, distance, color)) = tuple((
+// R 6 (#70c710)
+fn parse_line(input: &str) -> IResult<&str, (Direction, i64, &str)> {
+    tuple((
         terminated(alpha1, space1).map(|c| match c {
             "R" => East,
             "L" => West,
@@ -46,172 +55,165 @@ fn parse_instruction(input: &str) -> IResult<&str, Instruction> {
         }),
         terminated(complete::i64, space1),
         delimited(tag("(#"), alphanumeric1, tag(")")),
-    ))(input)?;
+    ))(input)
+}
+
+fn decode_hex(color: &str) -> Instruction {
+    let direction = match &color[5..6] {
+        "0" => East,
+        "1" => South,
+        "2" => West,
+        "3" => North,
+        _ => unreachable!("unexpected direction"),
+    };
+    let distance = i64::from_str_radix(&color[0..5], 16).unwrap();
+    Instruction {
+        direction,
+        distance,
+    }
+}
+
+pub fn parse(input: &str, encoding: Encoding) -> Vec<Instruction> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (_, (direction, distance, color)) = parse_line(line).unwrap();
+            match encoding {
+                Encoding::Plain => Instruction {
+                    direction,
+                    distance,
+                },
+                Encoding::Hex => decode_hex(color),
+            }
+        })
+        .collect()
+}
 
-    Ok((
-        input,
-        Instruction {
-            direction,
-            distance,
-            color,
-        },
-    ))
+/// The trench as a polygon through the middle of its corner cubes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lagoon {
+    pub vertices: Vec<I64Vec2>,
 }
 
-fn calculate_lagoon_size(instructions: Vec<Instruction>) -> String {
-    let mut dugout: HashSet<IVec2> = HashSet::new();
-    let mut position = IVec2 { x: 0, y: 0 };
-    let mut min_x: i32 = 0;
-    let mut min_y: i32 = 0;
-    let mut max_x = 0;
-    let mut max_y = 0;
+impl Lagoon {
+    pub fn dig(instructions: &[Instruction]) -> Lagoon {
+        let vertices = instructions
+            .iter()
+            .scan(I64Vec2 { x: 0, y: 0 }, |position, instruction| {
+                *position += instruction.direction.step() * instruction.distance;
+                Some(*position)
+            })
+            .collect();
+        Lagoon { vertices }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (I64Vec2, I64Vec2)> + '_ {
+        self.vertices
+            .iter()
+            .copied()
+            .zip(self.vertices.iter().copied().cycle().skip(1))
+    }
 
-    for instruction in instructions {
-        dugout.insert(position);
-        let (x_range, y_range, direction) = match instruction.direction {
-            North => (0..1, -(instruction.distance)..0, IVec2 { x: 0, y: -1 }),
-            East => (0..(instruction.distance), 0..1, IVec2 { x: 1, y: 0 }),
-            South => (0..1, 0..(instruction.distance), IVec2 { x: 0, y: 1 }),
-            West => (-(instruction.distance)..0, 0..1, IVec2 { x: -1, y: 0 }),
-        };
-        y_range.for_each(|_| {
-            x_range.clone().for_each(|_| {
-                position = position + direction;
-                min_x = min_x.min(position.x);
-                min_y = min_y.min(position.y);
-                max_x = max_x.max(position.x + 1);
-                max_y = max_y.max(position.y + 1);
-                dugout.insert(position);
-            });
-        });
+    /// Cubes dug out for the trench itself.
+    pub fn trench(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| {
+                let distance = (a - b).abs();
+                distance.x + distance.y
+            })
+            .sum()
     }
 
-    // draw it (comment this out when running)
-    // for y in min_y..max_y {
-    //     for x in min_y..max_x {
-    //         if dugout
-    //             .get(&IVec2 {
-    //                 x: x as i32,
-    //                 y: y as i32,
-    //             })
-    //             .is_some()
-    //         {
-    //             print!("#");
-    //         } else {
-    //             print!(".");
-    //         }
-    //     }
-    //     println!("");
-    // }
+    // TIL:
+    // shoelace algo + border but I didn't get it.
+    // needed to look up a bunch of hints here :(
+    // most credit: https://github.com/ChristopherBiscardi/advent-of-code/blob/main/2023/rust/day-18/src/part2.rs
+    /// Cubes inside the trench. The shoelace formula gives the polygon's area
+    /// and Pick's theorem turns that into whole cubes not on the edge.
+    pub fn interior(&self) -> i64 {
+        let twice_area = self
+            .edges()
+            .map(|(a, b)| a.x as i128 * b.y as i128 - a.y as i128 * b.x as i128)
+            .sum::<i128>()
+            .abs();
+        let twice_area = i64::try_from(twice_area).expect("lagoon too big for an i64");
+        (twice_area - self.trench()) / 2 + 1
+    }
 
-    // now walk each line and fill in positions that are in the polygon
-    for y in min_y..max_y {
+    /// Trench and interior together.
+    pub fn volume(&self) -> i64 {
+        self.interior() + self.trench()
+    }
+
+    /// Whether the cube at `p` is dug out, on the trench or inside it.
+    pub fn contains(&self, p: I64Vec2) -> bool {
         let mut inside = false;
-        for x in min_x..max_x {
-            if dugout
-                .get(&IVec2 {
-                    x: x as i32,
-                    y: y as i32,
-                })
-                .is_some()
-            {
-                // is there is a cell below, it's vertical or turning down, so toggle it
-                let below = IVec2 { x, y: y + 1 };
-                if dugout.get(&below).is_some() {
-                    inside = !inside;
-                }
-            } else {
-                if inside {
-                    dugout.insert(IVec2 {
-                        x: x as i32,
-                        y: y as i32,
-                    });
-                }
+        for (a, b) in self.edges() {
+            let (low, high) = (a.min(b), a.max(b));
+            if (low.x..=high.x).contains(&p.x) && (low.y..=high.y).contains(&p.y) {
+                return true;
+            }
+            // Cast a ray east, counting vertical edges that cross it,
+            // half-open so a corner isn't counted twice.
+            if a.x == b.x && a.x > p.x && (low.y..high.y).contains(&p.y) {
+                inside = !inside;
             }
         }
+        inside
     }
 
-    // println!("-----------------");
-    //
-    let mut count = 0;
-    for y in min_y..max_y {
-        for x in min_x..max_x {
-            if dugout
-                .get(&IVec2 {
-                    x: x as i32,
-                    y: y as i32,
-                })
-                .is_some()
-            {
-                count += 1;
-                // print!("#");
-                //} else {
-                // print!(".");
+    /// The lagoon shrunk to fit in `width` by `height` characters, each one
+    /// `#` if the cube in the middle of the patch it covers is dug out.
+    pub fn render(&self, width: i64, height: i64) -> String {
+        let min = self
+            .vertices
+            .iter()
+            .copied()
+            .reduce(I64Vec2::min)
+            .unwrap_or_default();
+        let max = self
+            .vertices
+            .iter()
+            .copied()
+            .reduce(I64Vec2::max)
+            .unwrap_or_default();
+        let extent = max - min + I64Vec2 { x: 1, y: 1 };
+        let scale = ((extent.x + width - 1) / width)
+            .max((extent.y + height - 1) / height)
+            .max(1);
+        let (columns, rows) = (
+            (extent.x + scale - 1) / scale,
+            (extent.y + scale - 1) / scale,
+        );
+        let mut out = String::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let patch = min + I64Vec2::new(column, row) * scale;
+                let middle = (patch + I64Vec2::splat(scale / 2)).min(max);
+                out.push(if self.contains(middle) { '#' } else { '.' });
             }
+            out.push('\n');
         }
+        out
     }
+}
 
-    count.to_string()
+fn volume(input: &str, encoding: Encoding) -> String {
+    Lagoon::dig(&parse(input, encoding)).volume().to_string()
+}
+
+pub fn day18_parse(input: &str) {
+    std::hint::black_box(parse(input, Encoding::Plain));
 }
 
 pub fn day18_part1(input: &str) -> String {
-    let (_, instructions) = separated_list1(newline, parse_instruction)(input).unwrap();
-    calculate_lagoon_size(instructions)
+    volume(input, Encoding::Plain)
 }
 
 pub fn day18_part2(input: &str) -> String {
-    let (_, instructions) = separated_list1(newline, parse_instruction)(input).unwrap();
-
-    // TIL:
-    // shoelace algo + border but I didn't get it.
-    // needed to look up a bunch of hints here :(
-    // most credit: https://github.com/ChristopherBiscardi/advent-of-code/blob/main/2023/rust/day-18/src/part2.rs
-    let positions = instructions
-        .iter()
-        .scan(I64Vec2 { x: 0, y: 0 }, |state, instruction| {
-            let distance = &instruction.color[0..5];
-            let direction = match &instruction.color[5..6] {
-                "0" => I64Vec2 { x: 1, y: 0 },
-                "1" => I64Vec2 { x: 0, y: -1 },
-                "2" => I64Vec2 { x: -1, y: 0 },
-                "3" => I64Vec2 { x: 0, y: 1 },
-                _ => unreachable!(),
-            };
-
-            let distance = i64::from_str_radix(distance, 16).unwrap();
-
-            *state += direction * distance;
-            Some(*state)
-        })
-        .collect_vec();
-
-    let border = positions
-        .iter()
-        .tuple_windows()
-        .map(|(a, b)| {
-            let distance = (*a - *b).abs();
-            distance.x + distance.y
-        })
-        .sum::<i64>()
-        + {
-            let one = positions.iter().last().unwrap();
-            let two = positions.first().unwrap();
-            let distance = (*two - *one).abs();
-            distance.x + distance.y
-        };
-
-    let area = ((positions
-        .iter()
-        .tuple_windows()
-        .map(|(one, two)| one.x * two.y - one.y * two.x)
-        .sum::<i64>()
-        + border)
-        / 2)
-    .abs()
-        + 1;
-
-    dbg!(area);
-    (area + border).to_string()
+    volume(input, Encoding::Hex)
 }
 
 #[cfg(test)]
@@ -271,6 +273,7 @@ U 2 (#7a21e3)
         ",
         "952408144115"
     )]
+    // The same loop as part 1's, counter-clockwise.
     #[case(
         "R 6 (#000060)
 U 5 (#000053)
@@ -278,9 +281,57 @@ L 8 (#000082)
 D 5 (#000051)
 R 2 (#000020)
 ",
-        "80"
+        "54"
     )]
     fn test_day18_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(day18_part2(input), expected);
     }
+
+    const INPUT: &str = "R 6 (#70c710)
+D 5 (#0dc571)
+L 2 (#5713f0)
+D 2 (#d2c081)
+R 2 (#59c680)
+D 2 (#411b91)
+L 5 (#8ceee2)
+U 2 (#caa173)
+L 1 (#1b58a2)
+U 2 (#caa171)
+R 2 (#7807d2)
+U 3 (#a77fa3)
+L 2 (#015232)
+U 2 (#7a21e3)";
+
+    #[test]
+    fn test_lagoon() {
+        let lagoon = Lagoon::dig(&parse(INPUT, Encoding::Plain));
+        assert_eq!(38, lagoon.trench());
+        assert_eq!(24, lagoon.interior());
+        assert_eq!(
+            "#######
+#######
+#######
+..#####
+..#####
+#######
+#####..
+#######
+.######
+.######
+",
+            lagoon.render(100, 100)
+        );
+        // Each character samples the second cube of a 2x2 patch.
+        assert_eq!("####\n.###\n####\n####\n####\n", lagoon.render(4, 5));
+
+        let hex = parse(INPUT, Encoding::Hex);
+        assert_eq!(
+            Instruction {
+                direction: East,
+                distance: 461937
+            },
+            hex[0]
+        );
+        assert_eq!(5, Lagoon::dig(&hex).render(5, 5).lines().count());
+    }
 }
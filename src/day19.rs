@@ -1,21 +1,44 @@
-use std::{collections::HashMap, ops::RangeInclusive};
+use std::{collections::HashMap, fmt, rc::Rc, str::FromStr};
 
 #[derive(Debug)]
-struct Part {
+pub struct Part {
     x: u32,
     m: u32,
     a: u32,
     s: u32,
 }
 
-#[derive(Debug, Copy, Clone)]
-enum XmasProperty {
+impl Part {
+    fn get(&self, property: XmasProperty) -> u32 {
+        match property {
+            X => self.x,
+            M => self.m,
+            A => self.a,
+            S => self.s,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum XmasProperty {
     X,
     M,
     A,
     S,
 }
 
+impl fmt::Display for XmasProperty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            X => "x",
+            M => "m",
+            A => "a",
+            S => "s",
+        };
+        write!(f, "{name}")
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 enum Operation {
     GreaterThan(XmasProperty, u32),
@@ -23,39 +46,12 @@ enum Operation {
 }
 
 impl Operation {
-    fn passes(&self, part: &Part) -> bool {
-        match self {
-            GreaterThan(property, value) => match property {
-                X => part.x > *value,
-                M => part.m > *value,
-                A => part.a > *value,
-                S => part.s > *value,
-            },
-            LessThan(property, value) => match property {
-                X => part.x < *value,
-                M => part.m < *value,
-                A => part.a < *value,
-                S => part.s < *value,
-            },
-        }
-    }
-
-    fn to_range(&self, passing: bool) -> RangeInclusive<u32> {
-        match self {
-            GreaterThan(_, value) => {
-                if passing {
-                    value + 1..=4000
-                } else {
-                    1..=*value
-                }
-            }
-            LessThan(_, value) => {
-                if passing {
-                    1..=(value - 1)
-                } else {
-                    *value..=4000
-                }
-            }
+    /// Every test as `property < value`, and whether passing it means
+    /// being below.
+    fn normalized(&self) -> (XmasProperty, u32, bool) {
+        match *self {
+            GreaterThan(property, value) => (property, value + 1, false),
+            LessThan(property, value) => (property, value, true),
         }
     }
 }
@@ -75,20 +71,239 @@ struct Workflow<'a> {
 use Operation::*;
 use XmasProperty::*;
 
-impl Workflow<'_> {
-    fn next(&self, part: &Part) -> &'_ str {
-        let rule = self.rules.iter().find(|rule| match &rule.test {
-            None => return true,
-            Some(op) => {
-                if op.passes(part) {
-                    return true;
+/// Every workflow inlined into one decision tree. Each split asks whether a
+/// rating is below a value.
+///
+/// Identical subtrees are only built once and shared, so a workflow used
+/// from several places doesn't get copied for each of them. That makes this
+/// really a DAG.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree {
+    Accept,
+    Reject,
+    Split {
+        property: XmasProperty,
+        value: u32,
+        below: Rc<Tree>,
+        above: Rc<Tree>,
+    },
+}
+
+/// The ratings still possible at some point in the tree, as half-open
+/// ranges in x, m, a, s order.
+type Bounds = [(u32, u32); 4];
+
+const ALL_RATINGS: Bounds = [(1, 4001); 4];
+
+fn index(property: XmasProperty) -> usize {
+    match property {
+        X => 0,
+        M => 1,
+        A => 2,
+        S => 3,
+    }
+}
+
+/// A split, keyed on its already-shared children.
+type SplitKey = (XmasProperty, u32, *const Tree, *const Tree);
+
+/// Inlines workflows from "in" down. Each reference to a workflow is still
+/// compiled on its own, since every path reaches it with different ratings,
+/// but the nodes it builds are shared with any identical ones already made.
+struct Compiler<'a> {
+    workflows: HashMap<&'a str, &'a Workflow<'a>>,
+    accept: Rc<Tree>,
+    reject: Rc<Tree>,
+    splits: HashMap<SplitKey, Rc<Tree>>,
+    inlining: Vec<&'a str>,
+}
+
+impl<'a> Compiler<'a> {
+    fn workflow(&mut self, name: &'a str, bounds: Bounds) -> Result<Rc<Tree>, String> {
+        match name {
+            "A" => return Ok(Rc::clone(&self.accept)),
+            "R" => return Ok(Rc::clone(&self.reject)),
+            _ => (),
+        }
+        if self.inlining.contains(&name) {
+            return Err(format!("workflow {name} loops back on itself"));
+        }
+        let workflow = *self
+            .workflows
+            .get(name)
+            .ok_or(format!("no workflow named {name}"))?;
+        self.inlining.push(name);
+        let tree = self.rules(name, &workflow.rules, bounds);
+        self.inlining.pop();
+        tree
+    }
+
+    fn rules(
+        &mut self,
+        name: &'a str,
+        rules: &[Rule<'a>],
+        bounds: Bounds,
+    ) -> Result<Rc<Tree>, String> {
+        let (rule, rest) = rules
+            .split_first()
+            .ok_or(format!("workflow {name} doesn't end with a catch-all"))?;
+        let Some(test) = rule.test else {
+            return self.workflow(rule.destination_workflow, bounds);
+        };
+        let (property, value, pass_below) = test.normalized();
+        let (low, high) = bounds[index(property)];
+        // Prune a side no rating can reach.
+        if value <= low || value >= high {
+            let passes = (value <= low) != pass_below;
+            return if passes {
+                self.workflow(rule.destination_workflow, bounds)
+            } else {
+                self.rules(name, rest, bounds)
+            };
+        }
+
+        let mut below_bounds = bounds;
+        below_bounds[index(property)].1 = value;
+        let mut above_bounds = bounds;
+        above_bounds[index(property)].0 = value;
+        let (below, above) = if pass_below {
+            (
+                self.workflow(rule.destination_workflow, below_bounds)?,
+                self.rules(name, rest, above_bounds)?,
+            )
+        } else {
+            (
+                self.rules(name, rest, below_bounds)?,
+                self.workflow(rule.destination_workflow, above_bounds)?,
+            )
+        };
+        // Both sides end up the same: the split doesn't matter. Being shared
+        // nodes this is a pointer check, and it only catches sides built the
+        // same way, not every pair that happens to accept the same parts.
+        if Rc::ptr_eq(&below, &above) {
+            return Ok(below);
+        }
+        let key = (property, value, Rc::as_ptr(&below), Rc::as_ptr(&above));
+        Ok(Rc::clone(self.splits.entry(key).or_insert_with(|| {
+            Rc::new(Tree::Split {
+                property,
+                value,
+                below,
+                above,
+            })
+        })))
+    }
+}
+
+impl Tree {
+    /// Fails on a workflow that's used but never defined, one that loops
+    /// back on itself, or one without a catch-all at the end.
+    fn compile(workflows: &[Workflow]) -> Result<Tree, String> {
+        let mut compiler = Compiler {
+            workflows: workflows
+                .iter()
+                .map(|workflow| (workflow.name, workflow))
+                .collect(),
+            accept: Rc::new(Tree::Accept),
+            reject: Rc::new(Tree::Reject),
+            splits: HashMap::new(),
+            inlining: vec![],
+        };
+        if !compiler.workflows.contains_key("in") {
+            return Err("no \"in\" workflow to start from".to_string());
+        }
+        let tree = compiler.workflow("in", ALL_RATINGS)?;
+        drop(compiler);
+        Ok(Rc::unwrap_or_clone(tree))
+    }
+
+    pub fn accepts(&self, part: &Part) -> bool {
+        match self {
+            Tree::Accept => true,
+            Tree::Reject => false,
+            Tree::Split {
+                property,
+                value,
+                below,
+                above,
+            } => {
+                if part.get(*property) < *value {
+                    below.accepts(part)
                 } else {
-                    return false;
+                    above.accepts(part)
                 }
             }
-        });
-        rule.expect("should have matched something")
-            .destination_workflow
+        }
+    }
+
+    /// How many rating combinations within `bounds` end up accepted.
+    fn count(&self, bounds: Bounds) -> u64 {
+        match self {
+            Tree::Accept => bounds
+                .iter()
+                .map(|(low, high)| (high - low) as u64)
+                .product(),
+            Tree::Reject => 0,
+            Tree::Split {
+                property,
+                value,
+                below,
+                above,
+            } => {
+                let mut below_bounds = bounds;
+                below_bounds[index(*property)].1 = *value;
+                let mut above_bounds = bounds;
+                above_bounds[index(*property)].0 = *value;
+                below.count(below_bounds) + above.count(above_bounds)
+            }
+        }
+    }
+
+    /// Accepted combinations of ratings from 1 to 4000.
+    pub fn accepted_combinations(&self) -> u64 {
+        self.count(ALL_RATINGS)
+    }
+
+    /// Splits and leaves, counting a shared subtree once.
+    pub fn node_count(&self) -> usize {
+        let mut ids = HashMap::new();
+        self.number(&mut ids, &mut vec![]);
+        ids.len()
+    }
+
+    /// Gives every distinct node an id in preorder, writing each one's DOT
+    /// the first time it's seen.
+    fn number(&self, ids: &mut HashMap<*const Tree, usize>, lines: &mut Vec<String>) -> usize {
+        if let Some(&id) = ids.get(&(self as *const Tree)) {
+            return id;
+        }
+        let id = ids.len();
+        ids.insert(self, id);
+        match self {
+            Tree::Accept => lines.push(format!("  n{id} [label=\"A\", shape=box];")),
+            Tree::Reject => lines.push(format!("  n{id} [label=\"R\", shape=box];")),
+            Tree::Split {
+                property,
+                value,
+                below,
+                above,
+            } => {
+                lines.push(format!("  n{id} [label=\"{property} < {value}\"];"));
+                let below = below.number(ids, lines);
+                let above = above.number(ids, lines);
+                lines.push(format!("  n{id} -> n{below} [label=\"yes\"];"));
+                lines.push(format!("  n{id} -> n{above} [label=\"no\"];"));
+            }
+        }
+        id
+    }
+
+    /// The tree as a Graphviz digraph, for `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec!["digraph workflows {".to_string()];
+        self.number(&mut HashMap::new(), &mut lines);
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
     }
 }
 
@@ -107,7 +322,7 @@ mod parser {
     // px{a<2006:qkq,m>2090:A,rfg}
     //
     // {x=787,m=2655,a=1222,s=2876}
-    pub fn all(input: &str) -> IResult<&str, (Vec<Workflow>, Vec<Part>)> {
+    pub fn all(input: &str) -> IResult<&str, (Vec<Workflow<'_>>, Vec<Part>)> {
         let (input, (workflows, parts)) = separated_pair(workflows, many1(newline), parts)(input)?;
         Ok((input, (workflows, parts)))
     }
@@ -141,24 +356,24 @@ mod parser {
         Ok((input, part))
     }
 
-    pub fn workflows(input: &str) -> IResult<&str, Vec<Workflow>> {
+    pub fn workflows(input: &str) -> IResult<&str, Vec<Workflow<'_>>> {
         let (input, workflows) = separated_list1(newline, workflow)(input)?;
         Ok((input, workflows))
     }
 
-    fn workflow(input: &str) -> IResult<&str, Workflow> {
+    fn workflow(input: &str) -> IResult<&str, Workflow<'_>> {
         // px{a<2006:qkq,m>2090:A,rfg}
         let (input, name) = alpha1(input)?;
         let (input, rules) = delimited(tag("{"), rules, tag("}"))(input)?;
         Ok((input, Workflow { name, rules }))
     }
 
-    fn rules(input: &str) -> IResult<&str, Vec<Rule>> {
+    fn rules(input: &str) -> IResult<&str, Vec<Rule<'_>>> {
         let (input, rules) = separated_list1(tag(","), rule)(input)?;
         Ok((input, rules))
     }
 
-    fn rule(input: &str) -> IResult<&str, Rule> {
+    fn rule(input: &str) -> IResult<&str, Rule<'_>> {
         let (input, xmas) = alpha1(input)?;
         let (input, test) = opt(alt((tag("<"), tag(">"))))(input)?;
         let (input, amount) = opt(complete::u32)(input)?;
@@ -206,171 +421,50 @@ mod parser {
 }
 
 use parser::all;
-use ranges::Ranges;
+
+impl FromStr for Tree {
+    type Err = String;
+
+    /// Compiles the workflows, on their own or followed by the parts as in
+    /// the puzzle input.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (rest, workflows) = match all(input) {
+            Ok((rest, (workflows, _))) => (rest, workflows),
+            Err(_) => {
+                parser::workflows(input).map_err(|e| format!("Couldn't parse workflows: {e}"))?
+            }
+        };
+        match rest.trim().lines().next() {
+            None => Tree::compile(&workflows),
+            Some(line) => Err(format!("Unexpected input after the workflows: {line}")),
+        }
+    }
+}
+
+pub fn day19_parse(input: &str) {
+    std::hint::black_box(all(input).unwrap());
+}
 
 pub fn day19_part1(input: &str) -> String {
     let (_, (workflows, parts)) = all(input).expect("parsing should succeed");
+    let tree = Tree::compile(&workflows).unwrap_or_else(|e| panic!("{e}"));
 
-    // we need a map of workflows so we can address them by their name
-    let workflows = workflows
-        .into_iter()
-        .map(|workflow| (workflow.name, workflow))
-        .collect::<HashMap<&str, Workflow>>();
-
-    // filter to accepted parts
     parts
         .iter()
-        .filter_map(|part| {
-            let mut workflow_name = "in";
-            while workflow_name != "A" && workflow_name != "R" {
-                let workflow = workflows
-                    .get(workflow_name)
-                    .expect("should always have the workflow");
-                workflow_name = workflow.next(part)
-            }
-            match workflow_name {
-                "A" => Some(part.x + part.m + part.a + part.s),
-                "R" => None,
-                _ => unreachable!("unpossible!"),
-            }
-        })
+        .filter(|part| tree.accepts(part))
+        .map(|part| part.x + part.m + part.a + part.s)
         .sum::<u32>()
         .to_string()
 }
 
 pub fn day19_part2(input: &str) -> String {
-    // each part component can be 1-4000
-    // there are 4 components to each part.
-    // there are 2.56e14 combinations of parts?
-    // the sample answer is 1.67...e14
-
-    // will a repeating pattern emerge? probably not.
-
-    // the workflows use ranges.
-    // walk each workflow and determine the ranges that lead to success?
     let (_, (workflows, _)) = all(input).expect("should parse");
-    // we need a map of workflows so we can address them by their name
-    let workflows = workflows
-        .into_iter()
-        .map(|workflow| (workflow.name, workflow))
-        .collect::<HashMap<&str, Workflow>>();
-
-    // This is path finding? recursion?
-    let mut paths = AcceptablePaths {
-        current_path: vec![],
-        acceptable_paths: vec![],
-        workflows: &workflows,
-    };
-    paths.acceptable_paths("in");
-
-    // paths.acceptable_paths can now be iterated to build up ranges
-    // the product of the valid ranges for each XMAS in each path then summed is the answer.
-    paths
-        .acceptable_paths
-        .iter()
-        .map(|path| {
-            //
-            let mut acceptable_x = Ranges::from(1..=4000);
-            let mut acceptable_m = Ranges::from(1..=4000);
-            let mut acceptable_a = Ranges::from(1..=4000);
-            let mut acceptable_s = Ranges::from(1..=4000);
-
-            path.iter().for_each(|(rule, required)| match rule.test {
-                None => (),
-                Some(test) => {
-                    let property = match test {
-                        GreaterThan(p, _) => p,
-                        LessThan(p, _) => p,
-                    };
-                    let range = test.to_range(*required);
-                    match property {
-                        X => {
-                            let r = Ranges::from(range);
-                            acceptable_x &= r;
-                        }
-                        M => {
-                            acceptable_m &= Ranges::from(range);
-                        }
-                        A => {
-                            acceptable_a &= Ranges::from(range);
-                        }
-                        S => {
-                            acceptable_s &= Ranges::from(range);
-                        }
-                    };
-                }
-            });
-            let mut x_cnt = acceptable_x
-                .as_slice()
-                .iter()
-                .fold(0, |acc, range| acc + range.into_iter().count());
-            let mut m_cnt = acceptable_m
-                .as_slice()
-                .iter()
-                .fold(0, |acc, range| acc + range.into_iter().count());
-            let mut a_cnt = acceptable_a
-                .as_slice()
-                .iter()
-                .fold(0, |acc, range| acc + range.into_iter().count());
-            let mut s_cnt = acceptable_s
-                .as_slice()
-                .iter()
-                .fold(0, |acc, range| acc + range.into_iter().count());
-
-            if x_cnt == 0 {
-                x_cnt = 4000;
-            }
-            if m_cnt == 0 {
-                m_cnt = 4000;
-            }
-            if a_cnt == 0 {
-                a_cnt = 4000;
-            }
-            if s_cnt == 0 {
-                s_cnt = 4000;
-            }
-            x_cnt as u64 * m_cnt as u64 * a_cnt as u64 * s_cnt as u64
-        })
-        .sum::<u64>()
+    Tree::compile(&workflows)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .accepted_combinations()
         .to_string()
 }
 
-#[derive(Debug)]
-struct AcceptablePaths<'a> {
-    current_path: Vec<(Rule<'a>, bool)>,
-    acceptable_paths: Vec<Vec<(Rule<'a>, bool)>>,
-    workflows: &'a HashMap<&'a str, Workflow<'a>>,
-}
-
-impl AcceptablePaths<'_> {
-    fn acceptable_paths(&'_ mut self, workflow: &'_ str) {
-        if workflow == "A" {
-            let last_path = self.current_path.clone();
-            self.acceptable_paths.push(last_path);
-            return;
-        } else if workflow == "R" {
-            return;
-        } else {
-            // otherwise, recurse in to each branch of the next rule
-            let workflow = self.workflows.get(workflow).expect("unpossibruh");
-            workflow.rules.iter().enumerate().for_each(|(_, rule)| {
-                self.current_path.push((*rule, true));
-                self.acceptable_paths(rule.destination_workflow);
-                self.current_path.pop();
-
-                // push the false branch now and go to the next one
-                self.current_path.push((*rule, false));
-            });
-
-            // Pop each false that we pushed
-            for _ in 0..workflow.rules.len() {
-                self.current_path.pop();
-            }
-            return;
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::parser::*;
@@ -463,4 +557,78 @@ hdj{m>838:A,pv}
     fn test_day19_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(day19_part2(input), expected);
     }
+
+    #[test]
+    fn test_compile() {
+        // lnx and gd send everything the same way, so their rules merge away.
+        let (_, compiled) = workflows(
+            "in{s<1351:gd,lnx}
+lnx{m>1548:A,A}
+gd{a>3333:R,R}",
+        )
+        .unwrap();
+        let tree = Tree::compile(&compiled).unwrap();
+        assert_eq!(
+            Tree::Split {
+                property: S,
+                value: 1351,
+                below: Rc::new(Tree::Reject),
+                above: Rc::new(Tree::Accept),
+            },
+            tree
+        );
+        assert_eq!(
+            "digraph workflows {
+  n0 [label=\"s < 1351\"];
+  n1 [label=\"R\", shape=box];
+  n2 [label=\"A\", shape=box];
+  n0 -> n1 [label=\"yes\"];
+  n0 -> n2 [label=\"no\"];
+}
+",
+            tree.to_dot()
+        );
+        assert_eq!(2650 * 4000 * 4000 * 4000, tree.accepted_combinations());
+
+        // Once x < 1000, x > 2000 can never pass.
+        let (_, compiled) = workflows("in{x<1000:a,R}\na{x>2000:R,m<5:R,A}").unwrap();
+        let tree = Tree::compile(&compiled).unwrap();
+        // in's and a's R are the same node.
+        assert_eq!(4, tree.node_count());
+        assert_eq!(999 * 3996 * 4000 * 4000, tree.accepted_combinations());
+    }
+
+    #[test]
+    fn test_shared_workflows() {
+        // c is reached twice and built the same both times, so it's only in
+        // the tree once: x, a and m splits, then A and R.
+        let tree = "in{x<1000:c,p}\np{a<5:R,c}\nc{m<5:R,A}"
+            .parse::<Tree>()
+            .unwrap();
+        assert_eq!(5, tree.node_count());
+        assert_eq!(
+            999 * 3996 * 4000 * 4000 + 3001 * 3996 * 3996 * 4000,
+            tree.accepted_combinations()
+        );
+        assert_eq!(1, tree.to_dot().matches("m < 5").count());
+    }
+
+    #[test]
+    fn test_tree_from_str() {
+        let with_parts = "in{x<1000:A,R}\n\n{x=787,m=2655,a=1222,s=2876}\n";
+        assert_eq!(Ok(3), with_parts.parse::<Tree>().map(|t| t.node_count()));
+        assert_eq!(
+            Err("Unexpected input after the workflows: oops".to_string()),
+            "in{x<1000:A,R}\noops".parse::<Tree>()
+        );
+    }
+
+    #[rstest]
+    #[case("in{x<1000:zz,R}", "no workflow named zz")]
+    #[case("in{x<1000:a,R}\na{m<5:in,A}", "workflow in loops back on itself")]
+    #[case("px{x<1000:A,R}", "no \"in\" workflow to start from")]
+    #[case("in{x<1000:A}", "workflow in doesn't end with a catch-all")]
+    fn test_compile_errors(#[case] input: &str, #[case] error: &str) {
+        assert_eq!(Err(error.to_string()), input.parse::<Tree>());
+    }
 }
@@ -1,52 +1,168 @@
-use self::parser::games;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+};
+
+use self::parser::{games, round};
+
+/// Some number of cubes of each colour. Colours that aren't there count as 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cubes(BTreeMap<String, u32>);
+
+impl Cubes {
+    pub fn get(&self, colour: &str) -> u32 {
+        self.0.get(colour).copied().unwrap_or(0)
+    }
+
+    pub fn colours(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// True when every colour here fits in `bag`.
+    pub fn fits_in(&self, bag: &Cubes) -> bool {
+        self.0
+            .iter()
+            .all(|(colour, count)| *count <= bag.get(colour))
+    }
+
+    /// The number of each colour multiplied together.
+    pub fn power(&self) -> u32 {
+        self.0.values().product()
+    }
+}
+
+impl<'a> FromIterator<(&'a str, u32)> for Cubes {
+    fn from_iter<T: IntoIterator<Item = (&'a str, u32)>>(iter: T) -> Self {
+        let mut cubes = Cubes::default();
+        for (colour, count) in iter {
+            *cubes.0.entry(colour.to_string()).or_default() += count;
+        }
+        cubes
+    }
+}
+
+/// `"12 red, 13 green, 14 blue"`
+impl FromStr for Cubes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match round(s) {
+            Ok(("", cubes)) => Ok(cubes),
+            _ => Err(format!("expected '<count> <colour>, ...', got '{s}'")),
+        }
+    }
+}
 
 #[derive(Debug)]
-struct Game {
-    id: u32,
-    rounds: Vec<(u32, u32, u32)>,
+pub struct Game {
+    pub id: u32,
+    pub rounds: Vec<Cubes>,
+}
+
+impl Game {
+    pub fn is_possible_with(&self, bag: &Cubes) -> bool {
+        self.rounds.iter().all(|round| round.fits_in(bag))
+    }
+
+    /// The fewest cubes of each colour seen that could have played this game.
+    pub fn minimum_bag(&self) -> Cubes {
+        let mut bag = Cubes::default();
+        for round in &self.rounds {
+            for (colour, count) in &round.0 {
+                let most = bag.0.entry(colour.clone()).or_default();
+                *most = (*most).max(*count);
+            }
+        }
+        bag
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ColourStats {
+    /// Most cubes shown in a single round.
+    pub max: u32,
+    /// Cubes shown, summed over every round.
+    pub total: u32,
+    /// Rounds the colour showed up in at all.
+    pub rounds: usize,
+}
+
+#[derive(Debug)]
+pub struct Games(pub Vec<Game>);
+
+impl Games {
+    pub fn parse(input: &str) -> Games {
+        let (_, games) = games(input).unwrap();
+        Games(games)
+    }
+
+    /// Every colour seen in any game.
+    pub fn colours(&self) -> BTreeSet<&str> {
+        self.0
+            .iter()
+            .flat_map(|game| game.rounds.iter().flat_map(|round| round.colours()))
+            .collect()
+    }
+
+    pub fn possible_with<'a>(&'a self, bag: &'a Cubes) -> impl Iterator<Item = &'a Game> {
+        self.0.iter().filter(|game| game.is_possible_with(bag))
+    }
+
+    /// Each game's minimum bag, with a 0 for any colour some other game used
+    /// but this one didn't.
+    pub fn minimum_bags(&self) -> Vec<(u32, Cubes)> {
+        let colours = self.colours();
+        self.0
+            .iter()
+            .map(|game| {
+                let mut bag = game.minimum_bag();
+                for colour in &colours {
+                    bag.0.entry(colour.to_string()).or_default();
+                }
+                (game.id, bag)
+            })
+            .collect()
+    }
+
+    pub fn stats(&self) -> BTreeMap<&str, ColourStats> {
+        let mut stats = BTreeMap::<&str, ColourStats>::new();
+        for round in self.0.iter().flat_map(|game| &game.rounds) {
+            for (colour, count) in &round.0 {
+                let stat = stats.entry(colour.as_str()).or_default();
+                stat.max = stat.max.max(*count);
+                stat.total += count;
+                stat.rounds += 1;
+            }
+        }
+        stats
+    }
 }
 
 mod parser {
     use nom::{
         bytes::complete::tag,
         character::complete::{alpha1, multispace1, newline, u32},
+        combinator::map,
         multi::{separated_list0, separated_list1},
         sequence::{preceded, separated_pair, terminated},
         IResult,
     };
 
-    use super::Game;
+    use super::{Cubes, Game};
 
-    fn color(input: &str) -> IResult<&str, (u32, &str)> {
-        let (input, color) = separated_pair(u32, multispace1, alpha1)(input)?;
-        Ok((input, color))
+    fn color(input: &str) -> IResult<&str, (&str, u32)> {
+        let (input, (count, color)) = separated_pair(u32, multispace1, alpha1)(input)?;
+        Ok((input, (color, count)))
     }
 
-    fn round(input: &str) -> IResult<&str, Vec<(u32, &str)>> {
-        let (input, colors) = separated_list0(tag(", "), color)(input)?;
-        Ok((input, colors))
+    pub fn round(input: &str) -> IResult<&str, Cubes> {
+        map(separated_list0(tag(", "), color), Cubes::from_iter)(input)
     }
 
     fn game(input: &str) -> IResult<&str, Game> {
         let (input, id) = terminated(preceded(tag("Game "), u32), tag(": "))(input)?;
         let (input, rounds) = separated_list1(tag("; "), round)(input)?;
-        let mut game = Game {
-            id,
-            rounds: Vec::new(),
-        };
-
-        rounds.iter().for_each(|parsed_round| {
-            let mut round = (0, 0, 0);
-            parsed_round.iter().for_each(|color| match color.1 {
-                "red" => round.0 = color.0,
-                "green" => round.1 = color.0,
-                "blue" => round.2 = color.0,
-                _ => panic!(),
-            });
-            game.rounds.push(round)
-        });
-
-        Ok((input, game))
+        Ok((input, Game { id, rounds }))
     }
 
     pub fn games(input: &str) -> IResult<&str, Vec<Game>> {
@@ -55,42 +171,25 @@ mod parser {
     }
 }
 
+pub fn day2_parse(input: &str) {
+    std::hint::black_box(Games::parse(input));
+}
+
 pub fn day2_part1(input: &str) -> String {
-    let (_, games) = games(input).unwrap();
-    let max_possible = (12, 13, 14);
+    let games = Games::parse(input);
+    let bag = "12 red, 13 green, 14 blue".parse::<Cubes>().unwrap();
     games
-        .iter()
-        .filter_map(|game| {
-            let all = game.rounds.iter().all(|round| {
-                if round.0 <= max_possible.0
-                    && round.1 <= max_possible.1
-                    && round.2 <= max_possible.2
-                {
-                    true
-                } else {
-                    false
-                }
-            });
-            if all {
-                Some(game.id)
-            } else {
-                None
-            }
-        })
+        .possible_with(&bag)
+        .map(|game| game.id)
         .sum::<u32>()
         .to_string()
 }
 
 pub fn day2_part2(input: &str) -> String {
-    let (_, games) = games(input).unwrap();
-    games
+    Games::parse(input)
+        .minimum_bags()
         .iter()
-        .map(|game| {
-            let sums = game.rounds.iter().fold((0, 0, 0), |acc, round| {
-                (acc.0.max(round.0), acc.1.max(round.1), acc.2.max(round.2))
-            });
-            sums.0 * sums.1 * sums.2
-        })
+        .map(|(_, bag)| bag.power())
         .sum::<u32>()
         .to_string()
 }
@@ -115,4 +214,49 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
     fn test_day2_part2() {
         assert_eq!("2286", day2_part2(INPUT));
     }
+
+    #[test]
+    fn test_queries() {
+        let games = Games::parse(INPUT);
+        let small = "5 red, 5 green, 5 blue".parse::<Cubes>().unwrap();
+        assert_eq!(
+            vec![2],
+            games
+                .possible_with(&small)
+                .map(|g| g.id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "4 red, 2 green, 6 blue".parse::<Cubes>().unwrap(),
+            games.0[0].minimum_bag()
+        );
+        assert_eq!(
+            ColourStats {
+                max: 20,
+                total: 61,
+                rounds: 11
+            },
+            games.stats()["red"]
+        );
+    }
+
+    #[test]
+    fn test_new_colours() {
+        let games = Games::parse("Game 1: 3 blue, 2 purple; 1 red\nGame 2: 4 red");
+        assert_eq!(
+            vec!["blue", "purple", "red"],
+            games.colours().into_iter().collect::<Vec<_>>()
+        );
+        let bag = "3 blue, 1 red".parse::<Cubes>().unwrap();
+        assert_eq!(0, games.possible_with(&bag).count());
+        // Game 2 never showed blue or purple, so it can't have any power.
+        assert_eq!(
+            vec![6, 0],
+            games
+                .minimum_bags()
+                .iter()
+                .map(|(_, b)| b.power())
+                .collect::<Vec<_>>()
+        );
+    }
 }
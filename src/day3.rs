@@ -1,193 +1,167 @@
-use std::collections::HashMap;
-
 use itertools::Itertools;
-use Item::*;
 
-#[derive(Debug)]
-struct NumberDetails {
-    amount: i32,
-    position: (usize, usize),
-    length: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartNumber {
+    pub value: u32,
+    /// (x, y) of the first digit.
+    pub position: (usize, usize),
+    pub length: usize,
 }
 
-#[derive(Debug)]
-enum Item {
-    Number(NumberDetails),
-    Symbol((usize, usize), char),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol {
+    pub c: char,
+    pub position: (usize, usize),
 }
 
-impl Item {
-    fn position(&self) -> (usize, usize) {
-        match self {
-            Number(details) => details.position,
-            Symbol(position, _) => *position,
-        }
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Empty,
+    Number(usize),
+    Symbol(usize),
 }
 
-fn parse(input: &str) -> Vec<Item> {
-    let mut items: Vec<Item> = Vec::new();
-    let mut current = "".to_string();
-    input.lines().enumerate().for_each(|(y, line)| {
-        current = "".to_string();
-        line.chars().enumerate().for_each(|(x, c)| match c {
-            '0'..='9' => {
-                current.push_str(&c.to_string());
-
-                // yuck, this is done 3 times :(
-                if x + 1 == line.len() && current.len() > 0 {
-                    let amount = current.parse::<i32>().unwrap();
-                    items.push(Number(NumberDetails {
-                        amount,
-                        position: (x + 1 - current.len(), y),
-                        length: current.len(),
-                    }));
-                }
-            }
-            '.' => {
-                if current.len() > 0 {
-                    let amount = current.parse::<i32>().unwrap();
-                    items.push(Number(NumberDetails {
-                        amount,
-                        position: (x - current.len(), y),
-                        length: current.len(),
-                    }));
-                }
-                current = "".to_string();
-            }
-            c => {
-                if current.len() > 0 {
-                    let amount = current.parse::<i32>().unwrap();
-                    items.push(Number(NumberDetails {
-                        amount,
-                        position: (x - current.len(), y),
-                        length: current.len(),
-                    }));
-                }
-                items.push(Symbol((x, y), c));
-                current = "".to_string();
-            }
-        });
-    });
-
-    items
+/// An engine schematic with every cell pointing at the number or symbol on
+/// it, so neighbours are a lookup rather than a search.
+#[derive(Debug)]
+pub struct Schematic {
+    pub numbers: Vec<PartNumber>,
+    pub symbols: Vec<Symbol>,
+    cells: Vec<Vec<Cell>>,
 }
 
-impl Item {
-    fn is_number_and_touching(&self, map: &HashMap<(usize, usize), &Item>) -> Option<i32> {
-        match self {
-            Number(details) => {
-                // look at each occupied position and check its surroundings
-                // part1 end note: Lost so much time due to having 0..=details.position.0...
-                // instead of .0..details... :(
-                // so many of the tests didn't catch that and it didn't reproduce in small snippets. :/
-                for x in details.position.0..details.position.0 + details.length {
-                    let (x, y) = (x as i32, details.position.1 as i32);
-                    // Now look all around
-                    let x_range = x - 1..=x + 1;
-                    let y_range = y - 1..=y + 1;
-                    let positions = x_range
-                        .into_iter()
-                        .cartesian_product(y_range.into_iter())
-                        .collect::<Vec<_>>();
+impl Schematic {
+    pub fn parse(input: &str) -> Schematic {
+        let mut numbers: Vec<PartNumber> = Vec::new();
+        let mut symbols = Vec::new();
+        let mut cells = Vec::new();
 
-                    let any = positions.iter().any(|position| {
-                        let item = map.get(&(position.0 as usize, position.1 as usize));
-                        match item {
-                            Some(Symbol(_, ..)) => true,
-                            _ => false,
+        for (y, line) in input.trim().lines().enumerate() {
+            let mut row = Vec::with_capacity(line.len());
+            let mut current: Option<usize> = None;
+            for (x, c) in line.chars().enumerate() {
+                match c.to_digit(10) {
+                    Some(digit) => {
+                        let index = *current.get_or_insert_with(|| {
+                            numbers.push(PartNumber {
+                                value: 0,
+                                position: (x, y),
+                                length: 0,
+                            });
+                            numbers.len() - 1
+                        });
+                        numbers[index].value = numbers[index].value * 10 + digit;
+                        numbers[index].length += 1;
+                        row.push(Cell::Number(index));
+                    }
+                    None => {
+                        current = None;
+                        if c == '.' || c.is_whitespace() {
+                            row.push(Cell::Empty);
+                        } else {
+                            symbols.push(Symbol {
+                                c,
+                                position: (x, y),
+                            });
+                            row.push(Cell::Symbol(symbols.len() - 1));
                         }
-                    });
-
-                    if any {
-                        return Some(details.amount);
                     }
                 }
-                None
             }
-            _ => None,
+            cells.push(row);
+        }
+
+        Schematic {
+            numbers,
+            symbols,
+            cells,
         }
     }
 
-    fn gear_ratio(&self, map: &HashMap<(usize, usize), &Item>) -> Option<i32> {
-        match self {
-            Symbol((x, y), '*') => {
-                // Now look all around
-                let x_range = x - 1..=x + 1;
-                let y_range = y - 1..=y + 1;
-                let positions = x_range
-                    .into_iter()
-                    .cartesian_product(y_range.into_iter())
-                    .collect::<Vec<_>>();
+    fn cell(&self, x: isize, y: isize) -> Cell {
+        if x < 0 || y < 0 {
+            return Cell::Empty;
+        }
+        self.cells
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+            .unwrap_or(Cell::Empty)
+    }
 
-                let numbers = positions
-                    .iter()
-                    .filter_map(|position| {
-                        let item = map.get(&(position.0 as usize, position.1 as usize));
-                        match item {
-                            Some(Number(details)) => Some(details),
-                            _ => None,
-                        }
-                    })
-                    .unique_by(|details| details.position)
-                    .map(|details| details.amount)
-                    .collect::<Vec<_>>();
+    /// Indexes into `numbers` of every number next to symbol `symbol`.
+    pub fn numbers_touching_symbol(&self, symbol: usize) -> Vec<usize> {
+        let (x, y) = self.symbols[symbol].position;
+        let (x, y) = (x as isize, y as isize);
+        (y - 1..=y + 1)
+            .cartesian_product(x - 1..=x + 1)
+            .filter_map(|(y, x)| match self.cell(x, y) {
+                Cell::Number(number) => Some(number),
+                _ => None,
+            })
+            .sorted()
+            .dedup()
+            .collect()
+    }
 
-                if numbers.len() == 2 {
-                    Some(numbers.iter().product::<i32>())
-                } else {
-                    None
-                }
-            }
-            _ => None,
-        }
+    /// Indexes into `symbols` of every symbol next to number `number`.
+    pub fn symbols_touching_number(&self, number: usize) -> Vec<usize> {
+        let PartNumber {
+            position: (x, y),
+            length,
+            ..
+        } = self.numbers[number];
+        let (x, y) = (x as isize, y as isize);
+        (y - 1..=y + 1)
+            .cartesian_product(x - 1..=x + length as isize)
+            .filter_map(|(y, x)| match self.cell(x, y) {
+                Cell::Symbol(symbol) => Some(symbol),
+                _ => None,
+            })
+            .collect()
     }
-}
 
-pub fn day3_part1(input: &str) -> String {
-    let input = input.trim();
-    let items = parse(input);
-    let mut map: HashMap<(usize, usize), &Item> = HashMap::new();
+    /// The numbers next to at least one symbol.
+    pub fn part_numbers(&self) -> impl Iterator<Item = &PartNumber> {
+        (0..self.numbers.len())
+            .filter(|number| !self.symbols_touching_number(*number).is_empty())
+            .map(|number| &self.numbers[number])
+    }
 
-    for item in items.as_slice() {
-        map.insert(item.position(), item);
+    /// Every `gear` symbol with exactly `k` numbers around it, along with
+    /// those numbers.
+    pub fn gears(&self, gear: char, k: usize) -> Vec<(&Symbol, Vec<&PartNumber>)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| symbol.c == gear)
+            .filter_map(|(index, symbol)| {
+                let numbers = self.numbers_touching_symbol(index);
+                (numbers.len() == k)
+                    .then(|| (symbol, numbers.iter().map(|n| &self.numbers[*n]).collect()))
+            })
+            .collect()
     }
+}
 
-    items
-        .iter()
-        .filter_map(|item| item.is_number_and_touching(&map))
-        .sum::<i32>()
+pub fn day3_parse(input: &str) {
+    std::hint::black_box(Schematic::parse(input));
+}
+
+pub fn day3_part1(input: &str) -> String {
+    Schematic::parse(input)
+        .part_numbers()
+        .map(|number| number.value)
+        .sum::<u32>()
         .to_string()
 }
 
 pub fn day3_part2(input: &str) -> String {
-    let input = input.trim();
-    let items = parse(input);
-    let mut map: HashMap<(usize, usize), &Item> = HashMap::new();
-
-    for item in items.as_slice() {
-        match item {
-            Number(details) => {
-                (details.position.0..details.position.0 + details.length).for_each(|x| {
-                    map.insert((x, details.position.1), item);
-                });
-            }
-            _ => (),
-        }
-    }
-
-    let gears = items
-        .iter()
-        .filter(|item| match item {
-            Symbol(_, c) => return *c == '*',
-            _ => false,
-        })
-        .collect::<Vec<_>>();
-
-    gears
+    Schematic::parse(input)
+        .gears('*', 2)
         .iter()
-        .filter_map(|gear| gear.gear_ratio(&map))
-        .sum::<i32>()
+        .map(|(_, numbers)| numbers.iter().map(|n| n.value).product::<u32>())
+        .sum::<u32>()
         .to_string()
 }
 
@@ -271,4 +245,57 @@ mod test {
     fn test_day3_part2() {
         assert_eq!("467835", day3_part2(INPUT));
     }
+
+    #[test]
+    fn test_schematic_queries() {
+        let schematic = Schematic::parse(INPUT);
+        assert_eq!(10, schematic.numbers.len());
+        assert_eq!(6, schematic.symbols.len());
+
+        let star = schematic
+            .symbols
+            .iter()
+            .position(|s| s.position == (3, 1))
+            .unwrap();
+        let values = |numbers: Vec<usize>| {
+            numbers
+                .iter()
+                .map(|n| schematic.numbers[*n].value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![467, 35],
+            values(schematic.numbers_touching_symbol(star))
+        );
+
+        let lonely = schematic
+            .numbers
+            .iter()
+            .position(|n| n.value == 114)
+            .unwrap();
+        assert!(schematic.symbols_touching_number(lonely).is_empty());
+        let touching = schematic
+            .numbers
+            .iter()
+            .position(|n| n.value == 617)
+            .unwrap();
+        assert_eq!(
+            vec!['*'],
+            schematic
+                .symbols_touching_number(touching)
+                .iter()
+                .map(|s| schematic.symbols[*s].c)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(2, schematic.gears('*', 2).len());
+        assert_eq!(
+            vec![(3, 4)],
+            schematic
+                .gears('*', 1)
+                .iter()
+                .map(|(s, _)| s.position)
+                .collect::<Vec<_>>()
+        );
+    }
 }
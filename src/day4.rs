@@ -1,11 +1,76 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
 use self::parser::cards;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-struct Card {
-    winning_numbers: Vec<u32>,
-    my_numbers: Vec<u32>,
+pub struct Card {
+    pub id: u32,
+    pub winning_numbers: Vec<u32>,
+    pub my_numbers: Vec<u32>,
+}
+
+impl Card {
+    /// How many distinct winning numbers I have. A number listed twice only
+    /// counts once.
+    pub fn matches(&self) -> usize {
+        let winning = self.winning_numbers.iter().collect::<HashSet<_>>();
+        self.my_numbers
+            .iter()
+            .collect::<HashSet<_>>()
+            .intersection(&winning)
+            .count()
+    }
+
+    pub fn points(&self) -> u64 {
+        points_for(self.matches())
+    }
+}
+
+/// One point for the first match, doubled for each one after.
+pub fn points_for(matches: usize) -> u64 {
+    match matches {
+        0 => 0,
+        matches => 1u64
+            .checked_shl(matches as u32 - 1)
+            .expect("a card with more than 64 matches is worth more than a u64"),
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct CardReport {
+    pub id: u32,
+    pub matches: usize,
+    pub points: u64,
+    /// The original plus every copy won from earlier cards.
+    pub copies: u64,
+}
+
+/// Plays the whole pile. Each card hands its copy count to the next
+/// `matches` cards in one go (added to a running total now, taken back off
+/// where its run ends, rather than a loop per copy), so this is linear in
+/// the number of cards.
+pub fn cascade(cards: &[Card]) -> Vec<CardReport> {
+    let mut expiring = vec![0u64; cards.len() + 1];
+    let mut won = 0u64;
+    cards
+        .iter()
+        .enumerate()
+        .map(|(index, card)| {
+            won -= expiring[index];
+            let copies = won + 1;
+            let matches = card.matches();
+            if matches > 0 {
+                won += copies;
+                expiring[(index + 1 + matches).min(cards.len())] += copies;
+            }
+            CardReport {
+                id: card.id,
+                matches,
+                points: points_for(matches),
+                copies,
+            }
+        })
+        .collect()
 }
 
 mod parser {
@@ -20,7 +85,7 @@ mod parser {
     use super::Card;
 
     fn card(input: &str) -> IResult<&str, Card> {
-        let (input, _) = terminated(
+        let (input, id) = terminated(
             terminated(
                 preceded(terminated(tag("Card"), many1(tag(" "))), complete::u32),
                 tag(":"),
@@ -36,6 +101,7 @@ mod parser {
         Ok((
             input,
             Card {
+                id,
                 winning_numbers: numbers.0,
                 my_numbers: numbers.1,
             },
@@ -56,6 +122,7 @@ mod parser {
         fn test_card() {
             assert_eq!(
                 Card {
+                    id: 1,
                     winning_numbers: vec![41, 48, 83, 86, 17],
                     my_numbers: vec![83, 86, 6, 31, 17, 9, 48, 53]
                 },
@@ -69,6 +136,7 @@ mod parser {
         fn test_card_with_space() {
             assert_eq!(
                 Card {
+                    id: 3,
                     winning_numbers: vec![1, 21, 53, 59, 44],
                     my_numbers: vec![9, 82, 63, 72, 16, 21, 14, 1]
                 },
@@ -80,61 +148,23 @@ mod parser {
     }
 }
 
+pub fn day4_parse(input: &str) {
+    std::hint::black_box(cards(input));
+}
+
 pub fn day4_part1(input: &str) -> String {
-    let cards = cards(input);
-    cards
+    cards(input)
         .iter()
-        .map(|card| {
-            let mut score = 0;
-            card.winning_numbers.iter().for_each(|w| {
-                if card.my_numbers.contains(w) {
-                    if score == 0 {
-                        score = 1
-                    } else {
-                        score *= 2
-                    }
-                }
-            });
-            score
-        })
-        .sum::<u32>()
+        .map(Card::points)
+        .sum::<u64>()
         .to_string()
 }
 
 pub fn day4_part2(input: &str) -> String {
-    let cards = cards(input);
-    let mut copies_by_index = cards
-        .iter()
-        .enumerate()
-        .map(|(i, _)| (i, 0))
-        .collect::<HashMap<usize, u32>>();
-
-    for (index, card) in cards.iter().enumerate() {
-        let mut wins = 0;
-        card.winning_numbers.iter().for_each(|w| {
-            if card.my_numbers.contains(w) {
-                wins += 1;
-            }
-        });
-
-        let additional_runs = *copies_by_index.get(&index).unwrap();
-
-        for _ in 0..=additional_runs {
-            for i in (index + 1)..(index + 1 + wins) {
-                match copies_by_index.get_mut(&i) {
-                    Some(count) => *count += 1,
-                    None => {
-                        unreachable!("The HashMap should have been seeded with 0s for all indexes")
-                    }
-                }
-            }
-        }
-    }
-
-    copies_by_index
+    cascade(&cards(input))
         .iter()
-        .map(|(_, v)| v + 1)
-        .sum::<u32>()
+        .map(|report| report.copies)
+        .sum::<u64>()
         .to_string()
 }
 
@@ -158,4 +188,38 @@ Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
     fn test_day4_part2() {
         assert_eq!("30", day4_part2(INPUT));
     }
+
+    #[test]
+    fn test_cascade() {
+        let report = cascade(&cards(INPUT));
+        assert_eq!(
+            vec![
+                (4, 8, 1),
+                (2, 2, 2),
+                (2, 2, 4),
+                (1, 1, 8),
+                (0, 0, 14),
+                (0, 0, 1)
+            ],
+            report
+                .iter()
+                .map(|r| (r.matches, r.points, r.copies))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(6, report.last().unwrap().id);
+    }
+
+    #[test]
+    fn test_repeated_numbers_count_once() {
+        let card = &cards("Card 1: 5 5 7 | 5 5 5 8")[0];
+        assert_eq!(1, card.matches());
+        assert_eq!(1, card.points());
+    }
+
+    #[test]
+    fn test_points_for() {
+        assert_eq!(0, points_for(0));
+        assert_eq!(8, points_for(4));
+        assert_eq!(1 << 40, points_for(41));
+    }
 }
@@ -1,21 +1,42 @@
-use crate::day5::parser::parse;
+use std::fmt::Display;
+
+use crate::day5::parser::almanac;
 use itertools::Itertools;
 
-#[derive(Debug, Copy, Clone)]
-struct Range {
-    destination: u64,
-    source: u64,
-    length: u64,
+/// `length` values starting at `source` map to the same number of values
+/// starting at `destination`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Range {
+    pub destination: u64,
+    pub source: u64,
+    pub length: u64,
 }
 
-#[derive(Debug)]
-struct Map {
-    _title: String,
-    ranges: Vec<Range>,
+/// A piecewise function from one category to another: values inside one of
+/// the ranges are shifted, the rest map to themselves. Ranges are kept
+/// sorted by source.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Map {
+    pub source: String,
+    pub destination: String,
+    pub ranges: Vec<Range>,
 }
 
 impl Map {
-    fn to_destination(&self, from: u64) -> u64 {
+    fn new(source: String, destination: String, mut ranges: Vec<Range>) -> Map {
+        ranges.sort_by_key(|range| range.source);
+        Map {
+            source,
+            destination,
+            ranges,
+        }
+    }
+
+    fn identity(category: &str) -> Map {
+        Map::new(category.to_string(), category.to_string(), vec![])
+    }
+
+    pub fn to_destination(&self, from: u64) -> u64 {
         for range in &self.ranges {
             if range.source <= from && from < range.source + range.length {
                 let diff = from - range.source;
@@ -24,107 +45,269 @@ impl Map {
         }
         from
     }
-}
-
-mod parser {
-    use super::*;
 
-    pub fn parse(input: &str) -> Vec<Map> {
-        let mut lines = input.lines();
-        let _ = lines.next();
-        let mut title: &str = "";
-        let mut map_lines: Vec<&str> = Vec::new();
-        let mut maps: Vec<Map> = Vec::new();
-
-        fn create_map(map_lines: &Vec<&str>, title: String) -> Map {
-            let ranges = map_lines
-                .iter()
-                .map(|range| {
-                    let range = range
-                        .split(" ")
-                        .map(|r| r.parse::<u64>().unwrap())
-                        .collect::<Vec<u64>>();
-                    Range {
-                        destination: range[0],
-                        source: range[1],
-                        length: range[2],
-                    }
-                })
-                .collect::<Vec<_>>();
-
-            Map {
-                _title: title,
-                ranges,
+    /// Splits `start..start + length` at the edges of the ranges, giving the
+    /// pieces in order with where each one ends up. The unmapped pieces are
+    /// in there too, sent to themselves.
+    pub fn pieces(&self, start: u64, length: u64) -> Vec<Range> {
+        let end = start + length;
+        let mut cursor = start;
+        let mut pieces = Vec::new();
+        for range in &self.ranges {
+            let range_end = range.source + range.length;
+            if range_end <= cursor {
+                continue;
             }
+            if range.source >= end {
+                break;
+            }
+            if range.source > cursor {
+                pieces.push(Range {
+                    destination: cursor,
+                    source: cursor,
+                    length: range.source - cursor,
+                });
+                cursor = range.source;
+            }
+            let piece_end = range_end.min(end);
+            pieces.push(Range {
+                destination: range.destination + (cursor - range.source),
+                source: cursor,
+                length: piece_end - cursor,
+            });
+            cursor = piece_end;
+        }
+        if cursor < end {
+            pieces.push(Range {
+                destination: cursor,
+                source: cursor,
+                length: end - cursor,
+            });
         }
+        pieces
+    }
 
-        for line in lines {
-            if line.len() == 0 {
-                continue;
-            }
-            if line
-                .chars()
-                .collect::<Vec<_>>()
-                .first()
-                .unwrap()
-                .is_alphabetic()
-            {
-                if title.len() > 0 {
-                    maps.push(create_map(&map_lines, title.to_string()));
-                    map_lines = vec![];
+    /// Where every value of `start..start + length` ends up, as ranges.
+    pub fn map_range(&self, start: u64, length: u64) -> Vec<(u64, u64)> {
+        self.pieces(start, length)
+            .into_iter()
+            .map(|piece| (piece.destination, piece.length))
+            .collect()
+    }
+
+    /// The single map that does `self` and then `next`.
+    pub fn then(&self, next: &Map) -> Map {
+        let ranges = self
+            .pieces(0, u64::MAX)
+            .into_iter()
+            .flat_map(|piece| {
+                next.pieces(piece.destination, piece.length)
+                    .into_iter()
+                    .map(move |next_piece| Range {
+                        destination: next_piece.destination,
+                        source: piece.source + (next_piece.source - piece.destination),
+                        length: next_piece.length,
+                    })
+            })
+            .filter(|range| range.source != range.destination)
+            .collect();
+        Map::new(self.source.clone(), next.destination.clone(), ranges)
+    }
+
+    /// Every source value that lands in `start..start + length`, as ranges.
+    /// Works whether or not the map is one-to-one.
+    pub fn preimage(&self, start: u64, length: u64) -> Vec<(u64, u64)> {
+        let end = start + length;
+        let mut sources = self
+            .pieces(0, u64::MAX)
+            .into_iter()
+            .filter_map(|piece| {
+                let from = piece.destination.max(start);
+                let to = (piece.destination + piece.length).min(end);
+                (from < to).then(|| (piece.source + (from - piece.destination), to - from))
+            })
+            .collect::<Vec<_>>();
+        sources.sort();
+
+        let mut merged: Vec<(u64, u64)> = Vec::new();
+        for (source, length) in sources {
+            match merged.last_mut() {
+                Some((last, last_length)) if *last + *last_length == source => {
+                    *last_length += length
                 }
-                title = line;
-            } else {
-                map_lines.push(line);
+                _ => merged.push((source, length)),
             }
         }
-        maps.push(create_map(&map_lines, title.to_string()));
-        maps
+        merged
+    }
+
+    /// The map going the other way. `None` if two values end up in the same
+    /// place, since then there's no single value to send them back to.
+    pub fn inverse(&self) -> Option<Map> {
+        let mut pieces = self.pieces(0, u64::MAX);
+        pieces.sort_by_key(|piece| piece.destination);
+        let one_to_one = pieces
+            .iter()
+            .tuple_windows()
+            .all(|(a, b)| a.destination + a.length <= b.destination);
+        if !one_to_one {
+            return None;
+        }
+
+        let ranges = pieces
+            .into_iter()
+            .filter(|piece| piece.source != piece.destination)
+            .map(|piece| Range {
+                destination: piece.source,
+                source: piece.destination,
+                length: piece.length,
+            })
+            .collect();
+        Some(Map::new(
+            self.destination.clone(),
+            self.source.clone(),
+            ranges,
+        ))
     }
 }
 
-pub fn day5_part1(input: &str) -> String {
-    let mut lines = input.lines();
-    let seed_line = lines.next();
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}-to-{}:", self.source, self.destination)?;
+        for range in &self.ranges {
+            writeln!(
+                f,
+                "{}..{} -> {}..{}",
+                range.source,
+                range.source + range.length,
+                range.destination,
+                range.destination + range.length
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Almanac {
+    pub seeds: Vec<u64>,
+    pub maps: Vec<Map>,
+}
+
+impl Almanac {
+    /// The categories in the order the maps chain through them.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories = self
+            .maps
+            .iter()
+            .map(|map| map.source.as_str())
+            .collect::<Vec<_>>();
+        categories.extend(self.maps.last().map(|map| map.destination.as_str()));
+        categories
+    }
+
+    fn chain(&self, from: &str, to: &str) -> Option<Map> {
+        let mut composed = Map::identity(from);
+        while composed.destination != to {
+            let next = self
+                .maps
+                .iter()
+                .find(|map| map.source == composed.destination)?;
+            composed = composed.then(next);
+        }
+        Some(composed)
+    }
 
-    let seeds = seed_line.unwrap().split(": ").skip(1).next().unwrap();
-    let seeds = seeds.split(" ").collect::<Vec<_>>();
-    let seeds = seeds.iter().map(|d| d.parse::<u64>().unwrap());
+    /// One map straight from category `from` to category `to`, e.g. seed to
+    /// humidity. Going backwards through the chain (location to seed) needs
+    /// every map on the way to be one-to-one.
+    pub fn between(&self, from: &str, to: &str) -> Option<Map> {
+        self.chain(from, to)
+            .or_else(|| self.chain(to, from)?.inverse())
+    }
+
+    pub fn seed_to_location(&self) -> Map {
+        self.between("seed", "location").unwrap()
+    }
+}
+
+mod parser {
+    use super::*;
+
+    fn map(title: &str, lines: &[&str]) -> Map {
+        let (source, destination) = title.trim_end_matches(" map:").split_once("-to-").unwrap();
+        let ranges = lines
+            .iter()
+            .map(|range| {
+                let (destination, source, length) = range
+                    .split_whitespace()
+                    .map(|r| r.parse::<u64>().unwrap())
+                    .collect_tuple()
+                    .unwrap();
+                Range {
+                    destination,
+                    source,
+                    length,
+                }
+            })
+            .collect();
+        Map::new(source.to_string(), destination.to_string(), ranges)
+    }
 
-    let maps = parse(input);
+    pub fn parse(input: &str) -> Vec<Map> {
+        input
+            .split("\n\n")
+            .skip(1)
+            .map(|block| {
+                let lines = block.lines().filter(|line| !line.is_empty()).collect_vec();
+                map(lines[0], &lines[1..])
+            })
+            .collect()
+    }
 
-    seeds
-        .map(|seed| maps.iter().fold(seed, |next, map| map.to_destination(next)))
+    pub fn almanac(input: &str) -> Almanac {
+        let seeds = input
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("seeds: "))
+            .unwrap()
+            .split_whitespace()
+            .map(|seed| seed.parse::<u64>().unwrap())
+            .collect();
+        Almanac {
+            seeds,
+            maps: parse(input),
+        }
+    }
+}
+
+pub fn day5_parse(input: &str) {
+    std::hint::black_box(almanac(input));
+}
+
+pub fn day5_part1(input: &str) -> String {
+    let almanac = almanac(input);
+    let seed_to_location = almanac.seed_to_location();
+
+    almanac
+        .seeds
+        .iter()
+        .map(|seed| seed_to_location.to_destination(*seed))
         .min()
         .unwrap()
         .to_string()
 }
 
 pub fn day5_part2(input: &str) -> String {
-    let mut lines = input.lines();
-    let seed_line = lines.next();
-
-    let seeds = seed_line.unwrap().split(": ").skip(1).next().unwrap();
-    let seeds = seeds.split(" ").collect::<Vec<_>>();
-    let seeds = seeds.iter().map(|d| d.parse::<u64>().unwrap());
-
-    let maps = parse(input);
-
-    // Brute force! This takes minutes to run, but gets the correct answer.
-    seeds
-        .chunks(2)
-        .into_iter()
-        .map(|seed_chunk| {
-            let chunks = seed_chunk.collect::<Vec<u64>>();
-            assert_eq!(2, chunks.len());
-            (chunks[0]..chunks[0] + chunks[1])
-                .map(|seed| {
-                    maps.iter()
-                        .fold(seed, |next, map| map.to_destination(next).clone())
-                })
-                .min()
-                .unwrap()
-        })
+    let almanac = almanac(input);
+    let seed_to_location = almanac.seed_to_location();
+
+    almanac
+        .seeds
+        .iter()
+        .tuples()
+        .flat_map(|(start, length)| seed_to_location.map_range(*start, *length))
+        .map(|(location, _)| location)
         .min()
         .unwrap()
         .to_string()
@@ -177,4 +360,103 @@ humidity-to-location map:
     fn test_day5_part2() {
         assert_eq!("46", day5_part2(INPUT));
     }
+
+    #[test]
+    fn test_map_range() {
+        let map = &almanac(INPUT).maps[0];
+        assert_eq!(vec![(79, 1)], map.map_range(77, 1));
+        assert_eq!(vec![(40, 10), (52, 3)], map.map_range(40, 13));
+        assert_eq!(vec![(99, 1), (50, 2), (100, 5)], map.map_range(97, 8));
+    }
+
+    #[test]
+    fn test_seed_to_location() {
+        let almanac = almanac(INPUT);
+        let seed_to_location = almanac.seed_to_location();
+        for seed in 0..120 {
+            let expected = almanac
+                .maps
+                .iter()
+                .fold(seed, |next, map| map.to_destination(next));
+            assert_eq!(expected, seed_to_location.to_destination(seed));
+        }
+        assert!(seed_to_location.to_string().starts_with(
+            "seed-to-location:\n0..14 -> 22..36\n14..15 -> 43..44\n15..22 -> 36..43\n"
+        ));
+    }
+
+    #[test]
+    fn test_categories() {
+        assert_eq!(
+            vec![
+                "seed",
+                "soil",
+                "fertilizer",
+                "water",
+                "light",
+                "temperature",
+                "humidity",
+                "location"
+            ],
+            almanac(INPUT).categories()
+        );
+    }
+
+    #[test]
+    fn test_between() {
+        let almanac = almanac(INPUT);
+        let seed_to_humidity = almanac.between("seed", "humidity").unwrap();
+        assert_eq!("seed", seed_to_humidity.source);
+        assert_eq!("humidity", seed_to_humidity.destination);
+        // From the puzzle's walk through: seed 79, ... humidity 78, location 82.
+        assert_eq!(78, seed_to_humidity.to_destination(79));
+        assert_eq!(
+            82,
+            almanac
+                .between("soil", "location")
+                .unwrap()
+                .to_destination(81)
+        );
+        assert_eq!(None, almanac.between("seed", "nowhere"));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let almanac = almanac(INPUT);
+        let location_to_seed = almanac.between("location", "seed").unwrap();
+        assert_eq!("location", location_to_seed.source);
+        for seed in 0..120 {
+            let location = almanac.seed_to_location().to_destination(seed);
+            assert_eq!(seed, location_to_seed.to_destination(location));
+        }
+        assert_eq!(82, location_to_seed.to_destination(46));
+    }
+
+    #[test]
+    fn test_preimage() {
+        let seed_to_location = almanac(INPUT).seed_to_location();
+        assert_eq!(vec![(82, 1)], seed_to_location.preimage(46, 1));
+        let expected = (0..200)
+            .filter(|seed| seed_to_location.to_destination(*seed) < 44)
+            .collect_vec();
+        let preimage = seed_to_location
+            .preimage(0, 44)
+            .into_iter()
+            .flat_map(|(start, length)| start..start + length)
+            .collect_vec();
+        assert_eq!(expected, preimage);
+
+        // Not one-to-one: both 0 and 5 end up at 5.
+        let squash = Map::new(
+            "a".to_string(),
+            "b".to_string(),
+            vec![Range {
+                destination: 5,
+                source: 0,
+                length: 1,
+            }],
+        );
+        assert_eq!(vec![(0, 1), (5, 1)], squash.preimage(5, 1));
+        assert_eq!(None, squash.inverse());
+    }
 }
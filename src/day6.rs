@@ -1,17 +1,19 @@
 use itertools::Itertools;
+use num::{BigInt, One, Zero};
 
 #[derive(Debug, PartialEq)]
-struct Race {
-    time: u64,
-    distance: u64,
+pub struct Race {
+    pub time: BigInt,
+    pub distance: BigInt,
 }
+
 fn races(input: &str) -> Vec<Race> {
     input
         .trim()
         .lines()
         .chunks(2)
         .into_iter()
-        .map(|lines| {
+        .flat_map(|lines| {
             let lines = lines.collect_vec();
             let times = lines[0];
             let distances = lines[1];
@@ -22,37 +24,61 @@ fn races(input: &str) -> Vec<Race> {
                 .unwrap()
                 .trim()
                 .split(" ")
-                .filter_map(|t| t.trim().parse::<u64>().ok());
+                .filter_map(|t| t.trim().parse::<BigInt>().ok());
             let distances = distances
                 .split(":")
                 .last()
                 .unwrap()
                 .trim()
                 .split(" ")
-                .filter_map(|t| t.trim().parse::<u64>().ok());
+                .filter_map(|t| t.trim().parse::<BigInt>().ok());
             times
                 .zip(distances)
                 .map(|(time, distance)| Race { time, distance })
                 .collect_vec()
         })
-        .flatten()
         .collect::<Vec<Race>>()
 }
 
 impl Race {
-    fn winning_hold_times(&self) -> u64 {
-        let mut winners: Vec<u64> = Vec::new();
-
-        for time in 1..self.time {
-            let time_to_travel = self.time - time;
-            let speed = time;
-            let distance_traveled = speed * time_to_travel;
-            if distance_traveled > self.distance {
-                winners.push(time)
+    /// Holding for `t` goes `t * (time - t)`, which beats the record while
+    /// `t² - time·t + distance < 0`, i.e. strictly between the roots
+    /// `(time ± √(time² - 4·distance)) / 2`. The integer square root gets
+    /// within one of the first winner, then the exact check settles it, so a
+    /// hold that only ties the record is never counted.
+    pub fn winning_interval(&self) -> Option<(BigInt, BigInt)> {
+        let discriminant = &self.time * &self.time - BigInt::from(4) * &self.distance;
+        if discriminant <= BigInt::zero() {
+            return None;
+        }
+        let wins = |hold: &BigInt| hold * (&self.time - hold) > self.distance;
+
+        let root: BigInt = discriminant.sqrt();
+        let mut first: BigInt = ((&self.time - root) / 2u32).max(BigInt::one());
+        while first > BigInt::one() && wins(&(&first - 1u32)) {
+            first -= 1u32;
+        }
+        while !wins(&first) {
+            first += 1u32;
+            if first >= self.time {
+                return None;
             }
         }
-        winners.len() as u64
+        // Holding for t goes as far as holding for time - t.
+        let last = &self.time - &first;
+        Some((first, last))
     }
+
+    pub fn winning_hold_times(&self) -> BigInt {
+        match self.winning_interval() {
+            Some((first, last)) => last - first + 1u32,
+            None => BigInt::zero(),
+        }
+    }
+}
+
+pub fn day6_parse(input: &str) {
+    std::hint::black_box(races(input));
 }
 
 pub fn day6_part1(input: &str) -> String {
@@ -60,7 +86,7 @@ pub fn day6_part1(input: &str) -> String {
     races
         .iter()
         .map(|race| race.winning_hold_times())
-        .product::<u64>()
+        .product::<BigInt>()
         .to_string()
 }
 
@@ -93,16 +119,16 @@ Distance:  9  40  200";
         assert_eq!(
             vec![
                 Race {
-                    time: 7,
-                    distance: 9
+                    time: 7.into(),
+                    distance: 9.into()
                 },
                 Race {
-                    time: 15,
-                    distance: 40
+                    time: 15.into(),
+                    distance: 40.into()
                 },
                 Race {
-                    time: 30,
-                    distance: 200
+                    time: 30.into(),
+                    distance: 200.into()
                 }
             ],
             races(INPUT)
@@ -113,8 +139,8 @@ Distance:  9  40  200";
     fn test_parser_p2() {
         assert_eq!(
             vec![Race {
-                time: 47707566,
-                distance: 282107911471062
+                time: 47707566.into(),
+                distance: 282107911471062u64.into()
             },],
             races(
                 "
@@ -134,4 +160,46 @@ Blah: 282107911471062
     fn test_day6_part2() {
         assert_eq!("71503", day6_part2(INPUT));
     }
+
+    #[test]
+    fn test_winning_interval() {
+        let race = |time: u64, distance: u64| Race {
+            time: time.into(),
+            distance: distance.into(),
+        };
+        let interval = |race: Race| {
+            race.winning_interval()
+                .map(|(first, last)| (first.to_string(), last.to_string()))
+        };
+        assert_eq!(Some(("2".into(), "5".into())), interval(race(7, 9)));
+        // 10 * 20 = 200 exactly ties the record, so 10 and 20 don't count.
+        assert_eq!(Some(("11".into(), "19".into())), interval(race(30, 200)));
+        // The only way to reach 16 is 4 * 4, a tie.
+        assert_eq!(None, interval(race(8, 16)));
+        assert_eq!(None, interval(race(8, 100)));
+        assert_eq!(Some(("1".into(), "7".into())), interval(race(8, 0)));
+    }
+
+    #[test]
+    fn test_big_races() {
+        let time = "1".to_string() + &"0".repeat(40);
+        let race = Race {
+            time: time.parse().unwrap(),
+            distance: "1".parse().unwrap(),
+        };
+        // Everything but 0 and the full time wins.
+        assert_eq!(
+            time.parse::<BigInt>().unwrap() - 1u32,
+            race.winning_hold_times()
+        );
+
+        // (10^20)^2 / 4 would be a tie at the midpoint, one less leaves
+        // only that single hold.
+        let time = BigInt::from(10).pow(20u32) * 2;
+        let race = Race {
+            distance: &time * &time / 4u32 - 1u32,
+            time,
+        };
+        assert_eq!(BigInt::one(), race.winning_hold_times());
+    }
 }
@@ -1,143 +1,168 @@
-use std::{cmp::Ordering, collections::BTreeMap};
+use std::collections::BTreeMap;
 
-#[derive(Debug, PartialEq)]
-struct Hand {
-    bid: u32,
-    r#type: Type,
-    cards: String,
+/// A hand type and the card counts, largest first, that make it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandType {
+    pub name: String,
+    pub signature: Vec<usize>,
 }
 
-fn build_hand(bid: u32, cards: String, part2: bool) -> Hand {
-    let cards = cards
-        .replace("A", "Z")
-        .replace("K", "Y")
-        .replace("Q", "X")
-        .replace("J", if part2 { "1" } else { "W" })
-        .replace("T", "V");
-    let t = type_from_cards(&cards, part2);
-    Hand {
-        bid,
-        r#type: t,
-        cards,
-    }
-}
-impl Eq for Hand {}
-
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // This could be tidied up, of course.
-        if self.r#type == other.r#type {
-            self.cards.cmp(&other.cards)
-        } else {
-            self.r#type.cmp(&other.r#type)
+impl HandType {
+    fn new(name: &str, signature: &[usize]) -> HandType {
+        HandType {
+            name: name.to_string(),
+            signature: signature.to_vec(),
         }
     }
 }
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
+/// How a game of Camel Cards is scored: which cards beat which, which cards
+/// are wild, and which hand types beat which.
+#[derive(Debug, Clone)]
+pub struct Ruleset {
+    /// Every card, weakest first.
+    pub order: Vec<char>,
+    pub wildcards: Vec<char>,
+    /// Every hand type, weakest first.
+    pub types: Vec<HandType>,
 }
 
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
-enum Type {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
-}
+impl Ruleset {
+    pub fn standard_types() -> Vec<HandType> {
+        vec![
+            HandType::new("high card", &[1, 1, 1, 1, 1]),
+            HandType::new("one pair", &[2, 1, 1, 1]),
+            HandType::new("two pair", &[2, 2, 1]),
+            HandType::new("three of a kind", &[3, 1, 1]),
+            HandType::new("full house", &[3, 2]),
+            HandType::new("four of a kind", &[4, 1]),
+            HandType::new("five of a kind", &[5]),
+        ]
+    }
 
-use Type::*;
+    pub fn standard() -> Ruleset {
+        Ruleset {
+            order: "23456789TJQKA".chars().collect(),
+            wildcards: vec![],
+            types: Ruleset::standard_types(),
+        }
+    }
 
-fn type_from_cards(cards: &String, part2: bool) -> Type {
-    let cards = cards.chars().collect::<Vec<char>>();
-    let counts = cards
-        .iter()
-        .map(|card| (*card, cards.iter().filter(|c| **c == *card).count()))
-        .collect::<BTreeMap<char, usize>>();
-
-    let joker_count = if part2 {
-        counts.get(&'1').or(Some(&0)).unwrap()
-    } else {
-        &0
-    };
-    match counts.keys().count() {
-        1 => FiveOfAKind,
-        2 => {
-            let values = counts.values().collect::<Vec<&usize>>();
-            if *joker_count > 0 {
-                FiveOfAKind
-            } else if *values[0] == 1 || *values[0] == 4 {
-                FourOfAKind
-            } else {
-                FullHouse
-            }
+    /// J is a joker: it counts as whatever makes the best type, but is the
+    /// weakest card when breaking ties.
+    pub fn jokers() -> Ruleset {
+        Ruleset {
+            order: "J23456789TQKA".chars().collect(),
+            wildcards: vec!['J'],
+            types: Ruleset::standard_types(),
         }
-        3 => {
-            // Could be 3 of a kind + 2 individuals
-            // two pair + 1 individual
-            if counts.values().any(|c| *c == 3) {
-                match joker_count {
-                    1..=3 => FourOfAKind,
-                    _ => ThreeOfAKind,
-                }
+    }
+
+    fn type_of_signature(&self, signature: &[usize]) -> Option<usize> {
+        self.types
+            .iter()
+            .position(|hand_type| hand_type.signature == signature)
+    }
+
+    /// Index into `types` of the best type `cards` can be, trying every way
+    /// of spreading the wildcards over the other cards. Fails if the ruleset
+    /// has no type for the hand.
+    pub fn classify(&self, cards: &str) -> Result<usize, String> {
+        let mut counts = BTreeMap::<char, usize>::new();
+        let mut wild = 0;
+        for card in cards.chars() {
+            if self.wildcards.contains(&card) {
+                wild += 1;
             } else {
-                match joker_count {
-                    2 => FourOfAKind,
-                    1 => FullHouse,
-                    _ => TwoPair,
-                }
+                *counts.entry(card).or_default() += 1;
             }
         }
-        4 => match joker_count {
-            2 => ThreeOfAKind,
-            1 => ThreeOfAKind,
-            _ => OnePair,
-        },
-        5 => {
-            if *joker_count > 0 {
-                OnePair
-            } else {
-                HighCard
-            }
+        let mut counts = counts.into_values().collect::<Vec<_>>();
+        self.best_type(&mut counts, wild)
+            .ok_or_else(|| format!("No hand type in the ruleset matches {cards}"))
+    }
+
+    fn best_type(&self, counts: &mut Vec<usize>, wild: usize) -> Option<usize> {
+        if wild == 0 {
+            let mut signature = counts.clone();
+            signature.sort_by(|a, b| b.cmp(a));
+            return self.type_of_signature(&signature);
+        }
+        let mut best = None;
+        for i in 0..counts.len() {
+            counts[i] += 1;
+            best = best.max(self.best_type(counts, wild - 1));
+            counts[i] -= 1;
         }
-        _ => unreachable!(),
+        counts.push(1);
+        best = best.max(self.best_type(counts, wild - 1));
+        counts.pop();
+        best
+    }
+
+    /// Where `card` sits in `order`, 0 being the weakest.
+    pub fn card_strength(&self, card: char) -> Result<usize, String> {
+        self.order
+            .iter()
+            .position(|c| *c == card)
+            .ok_or(format!("card {card} isn't in the ruleset"))
     }
 }
 
-fn hands(input: &str, part2: bool) -> Vec<Hand> {
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Hand {
+    // Field order matters: hands compare by type, then card by card.
+    r#type: usize,
+    strengths: Vec<usize>,
+    bid: u32,
+}
+
+impl Hand {
+    fn new(rules: &Ruleset, cards: &str, bid: u32) -> Result<Hand, String> {
+        Ok(Hand {
+            r#type: rules.classify(cards)?,
+            strengths: cards
+                .chars()
+                .map(|c| rules.card_strength(c))
+                .collect::<Result<_, _>>()?,
+            bid,
+        })
+    }
+}
+
+fn hands(input: &str, rules: &Ruleset) -> Result<Vec<Hand>, String> {
     input
         .lines()
         .map(|hand| {
-            let line = hand.split(" ").collect::<Vec<&str>>();
-            build_hand(line[1].parse::<u32>().unwrap(), line[0].to_string(), part2)
+            let (cards, bid) = hand.split_once(' ').unwrap();
+            Hand::new(rules, cards, bid.parse::<u32>().unwrap())
         })
-        .collect::<Vec<_>>()
+        .collect()
 }
 
-pub fn day7_part1(input: &str) -> String {
-    let mut hands = hands(input, false);
+pub fn total_winnings(input: &str, rules: &Ruleset) -> Result<u32, String> {
+    let mut hands = hands(input, rules)?;
     hands.sort();
-    hands
+    Ok(hands
         .iter()
         .enumerate()
         .map(|(i, hand)| hand.bid * (i as u32 + 1))
-        .sum::<u32>()
+        .sum::<u32>())
+}
+
+pub fn day7_parse(input: &str) {
+    std::hint::black_box(hands(input, &Ruleset::standard()).unwrap());
+}
+
+pub fn day7_part1(input: &str) -> String {
+    total_winnings(input, &Ruleset::standard())
+        .expect("the standard types cover every hand")
         .to_string()
 }
 
 pub fn day7_part2(input: &str) -> String {
-    let mut hands = hands(input, true);
-    hands.sort();
-    hands
-        .iter()
-        .enumerate()
-        .map(|(i, hand)| hand.bid * (i as u32 + 1))
-        .sum::<u32>()
+    total_winnings(input, &Ruleset::jokers())
+        .expect("the standard types cover every hand")
         .to_string()
 }
 
@@ -163,22 +188,74 @@ QQQJA 483";
 
     #[test]
     fn test_day7_part2_jokers() {
-        // Note the 1s here are jokers since type_from_cards runs post character conversion
-        assert_eq!(FiveOfAKind, type_from_cards(&"1111K".to_string(), true));
-        assert_eq!(FiveOfAKind, type_from_cards(&"KKK1K".to_string(), true));
-        assert_eq!(FiveOfAKind, type_from_cards(&"1KKK1".to_string(), true));
-        assert_eq!(OnePair, type_from_cards(&"23451".to_string(), true));
-        assert_eq!(ThreeOfAKind, type_from_cards(&"11KQT".to_string(), true));
-        assert_eq!(ThreeOfAKind, type_from_cards(&"1KKQT".to_string(), true));
-        assert_eq!(FourOfAKind, type_from_cards(&"11K1T".to_string(), true));
-        assert_eq!(FourOfAKind, type_from_cards(&"1KK1T".to_string(), true));
-        assert_eq!(FourOfAKind, type_from_cards(&"1KQQ1".to_string(), true));
-        assert_eq!(ThreeOfAKind, type_from_cards(&"1KTQ1".to_string(), true));
-        assert_eq!(FullHouse, type_from_cards(&"1KKQQ".to_string(), true));
-        assert_eq!(FourOfAKind, type_from_cards(&"1K1QQ".to_string(), true));
-        assert_eq!(ThreeOfAKind, type_from_cards(&"1KKQT".to_string(), true));
-        assert_eq!(FiveOfAKind, type_from_cards(&"KKK11".to_string(), true));
-        assert_eq!(FourOfAKind, type_from_cards(&"KQK11".to_string(), true));
-        assert_eq!(FourOfAKind, type_from_cards(&"KQKK1".to_string(), true));
+        let rules = Ruleset::jokers();
+        let type_of = |cards: &str| rules.types[rules.classify(cards).unwrap()].name.as_str();
+        assert_eq!("five of a kind", type_of("JJJJK"));
+        assert_eq!("five of a kind", type_of("KKKJK"));
+        assert_eq!("five of a kind", type_of("JKKKJ"));
+        assert_eq!("five of a kind", type_of("JJJJJ"));
+        assert_eq!("one pair", type_of("2345J"));
+        assert_eq!("three of a kind", type_of("JJKQT"));
+        assert_eq!("three of a kind", type_of("JKKQT"));
+        assert_eq!("four of a kind", type_of("JJKJT"));
+        assert_eq!("four of a kind", type_of("JKKJT"));
+        assert_eq!("four of a kind", type_of("JKQQJ"));
+        assert_eq!("three of a kind", type_of("JKTQJ"));
+        assert_eq!("full house", type_of("JKKQQ"));
+        assert_eq!("four of a kind", type_of("JKJQQ"));
+        assert_eq!("five of a kind", type_of("KKKJJ"));
+        assert_eq!("four of a kind", type_of("KQKJJ"));
+        assert_eq!("four of a kind", type_of("KQKKJ"));
+    }
+
+    #[test]
+    fn test_custom_rulesets() {
+        // Twos are wild too.
+        let mut rules = Ruleset::jokers();
+        rules.wildcards.push('2');
+        assert_eq!(
+            "four of a kind",
+            rules.types[rules.classify("J2KQQ").unwrap()].name
+        );
+
+        // Five different cards is no longer the worst hand: it beats three of a kind.
+        let mut rules = Ruleset::standard();
+        rules.types.insert(
+            4,
+            HandType {
+                name: "five different".to_string(),
+                signature: vec![1, 1, 1, 1, 1],
+            },
+        );
+        rules.types.remove(0);
+        assert_eq!(
+            "five different",
+            rules.types[rules.classify("2345K").unwrap()].name
+        );
+        assert!(rules.classify("2345K") > rules.classify("222QK"));
+        // 2345A climbs from worst to best.
+        let input = format!("{INPUT}\n2345A 10");
+        assert_eq!(Ok(6500), total_winnings(&input, &rules));
+
+        // Without five different, 2345A has no type at all.
+        rules.types.remove(3);
+        assert_eq!(
+            Err("No hand type in the ruleset matches 2345A".to_string()),
+            total_winnings(&input, &rules)
+        );
+
+        // Cards have to be in the order, wild or not.
+        assert_eq!(
+            Err("card Z isn't in the ruleset".to_string()),
+            total_winnings("2345Z 1", &Ruleset::standard())
+        );
+        let mut rules = Ruleset::standard();
+        rules.wildcards.push('*');
+        // It still makes a pair, but has no strength to break ties with.
+        assert_eq!(Ok(1), rules.classify("*2345"));
+        assert_eq!(
+            Err("card * isn't in the ruleset".to_string()),
+            total_winnings("*2345 1", &rules)
+        );
     }
 }
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, ops::ControlFlow};
+use std::collections::HashMap;
 
 use num::Integer;
 
@@ -37,7 +37,7 @@ mod parser {
         Ok((input, pieces))
     }
 
-    pub fn parse(input: &str) -> IResult<&str, Map> {
+    pub fn parse(input: &str) -> IResult<&str, Map<'_>> {
         let (input, (moves, directions)) =
             separated_pair(moves, many1(newline), separated_list1(newline, direction))(input)?;
         let directions = directions
@@ -50,34 +50,156 @@ mod parser {
 fn steps_to_find_from(map: &Map, position: &str, end: &str) -> usize {
     let mut position = position;
     let mut steps = 0;
-    loop {
-        map.moves.iter().try_for_each(|m| {
-            if position.ends_with(end) {
-                return ControlFlow::Break(());
-            }
-            steps += 1;
-            let dest = map.directions.get(position).unwrap();
-            match m {
-                'L' => {
-                    position = dest.0;
-                }
-                'R' => {
-                    position = dest.1;
-                }
-                _ => unreachable!(),
-            }
-            ControlFlow::Continue(())
-        });
-        if position.ends_with(end) {
-            break;
-        }
+    while !position.ends_with(end) {
+        position = next(map, position, steps);
+        steps += 1;
     }
     steps
 }
 
+/// Where a ghost's walk settles into a loop. A walk's state is its node and
+/// how far through `moves` it is, so every walk loops eventually.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cycle<'a> {
+    pub start: &'a str,
+    /// First step that is part of the loop.
+    pub entry: usize,
+    pub period: usize,
+    /// Steps before `entry` that land on an end node.
+    pub lead_in_ends: Vec<usize>,
+    /// Steps in `entry..entry + period` that land on an end node. The ghost
+    /// is on an end again every `period` steps after each of these.
+    pub ends: Vec<usize>,
+}
+
+impl Cycle<'_> {
+    /// The LCM shortcut assumes a ghost is on an end exactly at every
+    /// multiple of its period and nowhere else.
+    pub fn fits_lcm_shortcut(&self) -> bool {
+        self.lead_in_ends.is_empty() && self.ends == [self.period]
+    }
+
+    pub fn is_at_end(&self, step: usize) -> bool {
+        if step < self.entry {
+            self.lead_in_ends.contains(&step)
+        } else {
+            let lap = self.entry + (step - self.entry) % self.period;
+            self.ends.contains(&lap)
+        }
+    }
+}
+
+fn next<'a>(map: &Map<'a>, position: &str, step: usize) -> &'a str {
+    let dest = map.directions.get(position).unwrap();
+    match map.moves[step % map.moves.len()] {
+        'L' => dest.0,
+        'R' => dest.1,
+        _ => unreachable!(),
+    }
+}
+
+fn cycle<'a>(map: &Map<'a>, start: &'a str, is_end: impl Fn(&str) -> bool) -> Cycle<'a> {
+    let mut seen = HashMap::new();
+    let mut ends = vec![];
+    let mut position = start;
+    let mut step = 0;
+    let entry = loop {
+        if let Some(entry) = seen.insert((position, step % map.moves.len()), step) {
+            break entry;
+        }
+        if is_end(position) {
+            ends.push(step);
+        }
+        position = next(map, position, step);
+        step += 1;
+    };
+    let lead_in_ends = ends.iter().copied().filter(|end| *end < entry).collect();
+    ends.retain(|end| *end >= entry);
+    Cycle {
+        start,
+        entry,
+        period: step - entry,
+        lead_in_ends,
+        ends,
+    }
+}
+
+fn ghost_cycles<'a>(map: &Map<'a>) -> Vec<Cycle<'a>> {
+    let mut starts = map
+        .directions
+        .keys()
+        .filter(|position| position.ends_with('A'))
+        .copied()
+        .collect::<Vec<_>>();
+    starts.sort();
+    starts
+        .into_iter()
+        .map(|start| cycle(map, start, |position| position.ends_with('Z')))
+        .collect()
+}
+
+/// Merges `t ≡ a.0 (mod a.1)` and `t ≡ b.0 (mod b.1)`, moduli needn't be coprime.
+fn crt(a: (i128, i128), b: (i128, i128)) -> Option<(i128, i128)> {
+    let gcd = a.1.extended_gcd(&b.1);
+    let difference = b.0 - a.0;
+    if difference % gcd.gcd != 0 {
+        return None;
+    }
+    let modulus = a.1 / gcd.gcd * b.1;
+    let k = (difference / gcd.gcd * gcd.x).rem_euclid(b.1 / gcd.gcd);
+    Some(((a.0 + a.1 * k).rem_euclid(modulus), modulus))
+}
+
+/// The first step every ghost is on an end at once, if there is one.
+pub fn first_shared_end(cycles: &[Cycle]) -> Option<usize> {
+    if cycles.iter().all(|cycle| cycle.fits_lcm_shortcut()) {
+        return Some(cycles.iter().fold(1, |a, cycle| a.lcm(&cycle.period)));
+    }
+
+    // Before every ghost is in its loop, just try each step the first ghost
+    // could be on an end.
+    let settled = cycles.iter().map(|cycle| cycle.entry).max()?;
+    let first = &cycles[0];
+    let early = first
+        .lead_in_ends
+        .iter()
+        .copied()
+        .chain((first.entry..settled).filter(|step| first.is_at_end(*step)))
+        .find(|step| cycles.iter().all(|cycle| cycle.is_at_end(*step)));
+    if early.is_some() {
+        return early;
+    }
+
+    // After that each ghost is a choice of congruences; try every combination.
+    let mut solutions = vec![(0, 1)];
+    for cycle in cycles {
+        solutions = solutions
+            .iter()
+            .flat_map(|solution| {
+                cycle
+                    .ends
+                    .iter()
+                    .filter_map(|end| crt(*solution, (*end as i128, cycle.period as i128)))
+            })
+            .collect();
+    }
+    solutions
+        .into_iter()
+        .map(|(residue, modulus)| {
+            let settled = settled as i128;
+            let behind = (settled - residue).max(0);
+            (residue + (behind + modulus - 1) / modulus * modulus) as usize
+        })
+        .min()
+}
+
+pub fn day8_parse(input: &str) {
+    std::hint::black_box(parser::parse(input).unwrap());
+}
+
 pub fn day8_part1(input: &str) -> String {
     let (remainder, map) = parser::parse(input).unwrap();
-    assert!(remainder.trim().len() == 0);
+    assert!(remainder.trim().is_empty());
 
     let steps = steps_to_find_from(&map, "AAA", "ZZZ");
     steps.to_string()
@@ -85,21 +207,10 @@ pub fn day8_part1(input: &str) -> String {
 
 pub fn day8_part2(input: &str) -> String {
     let (remainder, map) = parser::parse(input).unwrap();
-    assert!(remainder.trim().len() == 0);
+    assert!(remainder.trim().is_empty());
 
-    // Break part 1 out to a helper "steps_to_find_from"
-    // that takes a start and ends_with.
-    // Find the path for each start and then get the LCM of those
-    map.directions
-        .keys()
-        .filter_map(|position| {
-            if position.chars().last().unwrap() == 'A' {
-                Some(steps_to_find_from(&map, position, "Z"))
-            } else {
-                None
-            }
-        })
-        .fold(1 as usize, |a, b| a.lcm(&b))
+    first_shared_end(&ghost_cycles(&map))
+        .expect("the ghosts are never all on an end at once")
         .to_string()
 }
 
@@ -150,4 +261,74 @@ XXX = (XXX, XXX)",
     fn test_day8_part2(#[case] input: &str, #[case] expected: &str) {
         assert_eq!(expected, day8_part2(input));
     }
+
+    #[test]
+    fn test_cycles() {
+        let (_, map) = parser::parse(
+            "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)",
+        )
+        .unwrap();
+        let cycles = ghost_cycles(&map);
+        assert_eq!(
+            Cycle {
+                start: "11A",
+                entry: 1,
+                period: 2,
+                lead_in_ends: vec![],
+                ends: vec![2],
+            },
+            cycles[0]
+        );
+        assert!(cycles[0].fits_lcm_shortcut());
+        // 22Z comes round every 3 steps, but the walk only repeats after 6.
+        assert_eq!(
+            (1, 6, vec![3, 6]),
+            (cycles[1].entry, cycles[1].period, cycles[1].ends.clone())
+        );
+        assert!(!cycles[1].fits_lcm_shortcut());
+        assert_eq!(Some(6), first_shared_end(&cycles));
+    }
+
+    #[test]
+    fn test_day8_part2_without_shortcut() {
+        // 1Z is reached at steps 1, 4, 7, ... and 2Z at 5, 10, 15, ..., so the
+        // LCM of the first arrivals (5) is wrong.
+        let input = "L
+
+1A = (1Z, 1Z)
+1Z = (1B, 1B)
+1B = (1C, 1C)
+1C = (1Z, 1Z)
+2A = (2B, 2B)
+2B = (2C, 2C)
+2C = (2D, 2D)
+2D = (2E, 2E)
+2E = (2Z, 2Z)
+2Z = (2B, 2B)";
+        assert_eq!("10", day8_part2(input));
+
+        // Odd steps only against even steps only: never together.
+        let (_, map) = parser::parse(
+            "L
+
+1A = (1Z, 1Z)
+1Z = (1B, 1B)
+1B = (1Z, 1Z)
+2A = (2B, 2B)
+2B = (2Z, 2Z)
+2Z = (2B, 2B)",
+        )
+        .unwrap();
+        let cycles = ghost_cycles(&map);
+        assert_eq!(None, first_shared_end(&cycles));
+    }
 }
@@ -1,48 +1,95 @@
-use itertools::Itertools;
+use num::{BigInt, Zero};
 
-fn find_next_value(line: &Vec<i32>, front: bool) -> i32 {
-    if line.iter().all(|i| *i == 0) {
-        return 0;
+/// A sequence that really is a polynomial, kept as the first value of each
+/// row of differences so it can be evaluated anywhere with Newton's forward
+/// difference formula.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polynomial {
+    leading_differences: Vec<BigInt>,
+    len: usize,
+}
+
+/// Differences never reach a row of zeros before running out of values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotPolynomial;
+
+impl Polynomial {
+    pub fn fit(values: &[BigInt]) -> Result<Polynomial, NotPolynomial> {
+        let mut leading_differences = vec![];
+        let mut row = values.to_vec();
+        while !row.iter().all(Zero::is_zero) {
+            if row.len() < 2 {
+                return Err(NotPolynomial);
+            }
+            leading_differences.push(row[0].clone());
+            row = row.windows(2).map(|pair| &pair[1] - &pair[0]).collect();
+        }
+        Ok(Polynomial {
+            leading_differences,
+            len: values.len(),
+        })
     }
 
-    let next_row = line
-        .iter()
-        .tuple_windows()
-        .map(|(a, b)| b - a)
-        .collect::<Vec<i32>>();
-
-    if front {
-        line.first().unwrap() - find_next_value(&next_row, front)
-    } else {
-        line.last().unwrap() + find_next_value(&next_row, front)
+    /// `None` for the zero sequence.
+    pub fn degree(&self) -> Option<usize> {
+        self.leading_differences.len().checked_sub(1)
+    }
+
+    /// The value at index `n`, where the sequence starts at 0. Negative `n`
+    /// works too: the binomial coefficients generalise.
+    pub fn value_at(&self, n: i64) -> BigInt {
+        let n = BigInt::from(n);
+        let mut binomial = BigInt::from(1);
+        let mut value = BigInt::zero();
+        for (i, difference) in self.leading_differences.iter().enumerate() {
+            value += &binomial * difference;
+            binomial = binomial * (&n - i) / (i + 1);
+        }
+        value
+    }
+
+    /// `steps` past the last value, or before the first when negative.
+    pub fn extrapolate(&self, steps: i64) -> BigInt {
+        if steps < 0 {
+            self.value_at(steps)
+        } else {
+            self.value_at(self.len as i64 - 1 + steps)
+        }
     }
 }
 
-fn parse(input: &str) -> Vec<Vec<i32>> {
+fn parse(input: &str) -> Vec<Vec<BigInt>> {
     input
         .lines()
         .map(|line| {
-            line.split(" ")
-                .map(|v| v.parse::<i32>().unwrap())
-                .collect::<Vec<i32>>()
+            line.split(' ')
+                .map(|v| v.parse::<BigInt>().unwrap())
+                .collect::<Vec<_>>()
         })
         .collect::<Vec<_>>()
 }
 
-pub fn day9_part1(input: &str) -> String {
+fn sum_extrapolated(input: &str, steps: i64) -> BigInt {
     parse(input)
         .iter()
-        .map(|line| find_next_value(line, false))
-        .sum::<i32>()
-        .to_string()
+        .enumerate()
+        .map(|(i, line)| match Polynomial::fit(line) {
+            Ok(polynomial) => polynomial.extrapolate(steps),
+            Err(NotPolynomial) => panic!("line {} isn't a polynomial", i + 1),
+        })
+        .sum()
+}
+
+pub fn day9_parse(input: &str) {
+    std::hint::black_box(parse(input));
+}
+
+pub fn day9_part1(input: &str) -> String {
+    sum_extrapolated(input, 1).to_string()
 }
 
 pub fn day9_part2(input: &str) -> String {
-    parse(input)
-        .iter()
-        .map(|line| find_next_value(line, true))
-        .sum::<i32>()
-        .to_string()
+    sum_extrapolated(input, -1).to_string()
 }
 
 #[cfg(test)]
@@ -62,4 +109,37 @@ mod test {
     fn test_day9_part2() {
         assert_eq!("2", day9_part2(INPUT));
     }
+
+    fn fit(values: &[i64]) -> Result<Polynomial, NotPolynomial> {
+        Polynomial::fit(&values.iter().map(|v| BigInt::from(*v)).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        let triangles = fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        assert_eq!(Some(2), triangles.degree());
+        assert_eq!(BigInt::from(28), triangles.extrapolate(1));
+        assert_eq!(BigInt::from(55), triangles.extrapolate(4));
+        assert_eq!(BigInt::from(0), triangles.extrapolate(-1));
+        assert_eq!(BigInt::from(1), triangles.extrapolate(-3));
+        assert_eq!(None, fit(&[0, 0, 0]).unwrap().degree());
+        assert_eq!(Some(0), fit(&[7, 7]).unwrap().degree());
+    }
+
+    #[test]
+    fn test_big_values() {
+        // n^5 overflows an i64 well before n = 10^6.
+        let values = (1_000_000..1_000_010)
+            .map(|n| BigInt::from(n).pow(5))
+            .collect::<Vec<_>>();
+        let polynomial = Polynomial::fit(&values).unwrap();
+        assert_eq!(Some(5), polynomial.degree());
+        assert_eq!(BigInt::from(1_000_100).pow(5), polynomial.extrapolate(91));
+    }
+
+    #[test]
+    fn test_not_polynomial() {
+        assert_eq!(Err(NotPolynomial), fit(&[1, 2, 4, 8, 16]));
+        assert_eq!(Err(NotPolynomial), fit(&[5]));
+    }
 }
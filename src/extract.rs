@@ -0,0 +1,275 @@
+use std::{fs, io, path::PathBuf};
+
+/// A guess at the expected answer for one part, and which example it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Guess {
+    pub part: u8,
+    pub example: usize,
+    pub answer: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Puzzle {
+    pub examples: Vec<String>,
+    pub guesses: Vec<Guess>,
+}
+
+/// Reads a saved puzzle page, or fetches it when given an http(s) url (a
+/// local mirror, say).
+pub fn load_page(source: &str) -> Result<String, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        ureq::get(source)
+            .call()
+            .map_err(|e| e.to_string())?
+            .into_string()
+            .map_err(|e| e.to_string())
+    } else {
+        fs::read_to_string(source).map_err(|e| format!("{source}: {e}"))
+    }
+}
+
+/// Each `<article>` is one part. Every `<pre><code>` block is an example and
+/// the last `<code><em>` in an article is taken as that part's answer, for
+/// the last example seen before it.
+pub fn extract(html: &str) -> Puzzle {
+    let mut puzzle = Puzzle::default();
+    let articles = sections(html, "<article", "</article>");
+    let articles = if articles.is_empty() {
+        vec![html]
+    } else {
+        articles
+    };
+
+    for (part, article) in articles.iter().enumerate() {
+        let mut last_example = puzzle.examples.len().checked_sub(1);
+        let mut answer = None;
+        let mut at = 0;
+        while at < article.len() {
+            let rest = &article[at..];
+            let pre = rest.find("<pre><code>");
+            let em = rest.find("<code><em>");
+            match (pre, em) {
+                (Some(pre), em) if em.is_none_or(|em| pre < em) => {
+                    let start = pre + "<pre><code>".len();
+                    let Some(len) = rest[start..].find("</code></pre>") else {
+                        break;
+                    };
+                    let example = decode(&rest[start..start + len]);
+                    last_example = Some(match puzzle.examples.iter().position(|e| *e == example) {
+                        Some(existing) => existing,
+                        None => {
+                            puzzle.examples.push(example);
+                            puzzle.examples.len() - 1
+                        }
+                    });
+                    at += start + len;
+                }
+                (_, Some(em)) => {
+                    let start = em + "<code><em>".len();
+                    let Some(len) = rest[start..].find("</em></code>") else {
+                        break;
+                    };
+                    answer = Some((last_example, decode(&rest[start..start + len])));
+                    at += start + len;
+                }
+                _ => break,
+            }
+        }
+
+        if let Some((Some(example), answer)) = answer {
+            puzzle.guesses.push(Guess {
+                part: part as u8 + 1,
+                example,
+                answer,
+            });
+        }
+    }
+    puzzle
+}
+
+fn sections<'a>(html: &'a str, open: &str, close: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find(open) {
+        let Some(end) = rest[start..].find(close) else {
+            break;
+        };
+        found.push(&rest[start..start + end]);
+        rest = &rest[start + end + close.len()..];
+    }
+    found
+}
+
+/// Drops any markup inside a block and undoes the usual entities.
+fn decode(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+pub fn example_path(day: u8, example: usize) -> PathBuf {
+    PathBuf::from(format!("./input/examples/day{}-{}.txt", day, example + 1))
+}
+
+pub fn answers_path(day: u8) -> PathBuf {
+    PathBuf::from(format!("./input/examples/day{}.answers", day))
+}
+
+/// Writes `input/examples/day{x}-{n}.txt` for every example and
+/// `input/examples/day{x}.answers` with a `part example answer` line per guess.
+pub fn write_examples(day: u8, puzzle: &Puzzle) -> io::Result<()> {
+    fs::create_dir_all("./input/examples")?;
+    for (i, example) in puzzle.examples.iter().enumerate() {
+        fs::write(example_path(day, i), example)?;
+    }
+    let answers = puzzle
+        .guesses
+        .iter()
+        .map(|guess| format!("{} {} {}\n", guess.part, guess.example + 1, guess.answer))
+        .collect::<String>();
+    fs::write(answers_path(day), answers)
+}
+
+fn string_literal(example: &str) -> String {
+    format!(
+        "\"{}\"",
+        example
+            .trim_end_matches('\n')
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+    )
+}
+
+/// Fills in a day file that still has the blank template's `INPUT` and
+/// `assert_eq!("0", ...)` lines. Anything already edited by hand is left alone.
+pub fn fill_template(day: u8, source: &str, puzzle: &Puzzle) -> Option<String> {
+    let blank = "const INPUT: &str = \"\";";
+    let first = puzzle.guesses.first()?;
+    if !source.contains(blank) {
+        return None;
+    }
+
+    let mut inputs = vec![first.example];
+    let mut source = source.to_string();
+    for guess in &puzzle.guesses {
+        let input = match inputs.iter().position(|e| *e == guess.example) {
+            Some(0) => "INPUT".to_string(),
+            Some(i) => format!("INPUT{}", i + 1),
+            None => {
+                inputs.push(guess.example);
+                format!("INPUT{}", inputs.len())
+            }
+        };
+        let test = format!("fn test_day{}_part{}() {{", day, guess.part);
+        source = source.replace(&format!("#[ignore]\n    {test}"), &test);
+        source = source.replace(
+            &format!("assert_eq!(\"0\", day{}_part{}(INPUT));", day, guess.part),
+            &format!(
+                "assert_eq!(\"{}\", day{}_part{}({input}));",
+                guess.answer, day, guess.part
+            ),
+        );
+    }
+
+    let consts = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, example)| {
+            let name = if i == 0 {
+                "INPUT".to_string()
+            } else {
+                format!("INPUT{}", i + 1)
+            };
+            format!(
+                "const {name}: &str = {};",
+                string_literal(&puzzle.examples[*example])
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n    ");
+    Some(source.replace(blank, &consts))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PAGE: &str = r#"<html><body><main>
+<article class="day-desc"><h2>--- Day 9: Mirage Maintenance ---</h2>
+<p>For example:</p>
+<pre><code>0 3 6 9 12 15
+1 3 6 10 15 21
+</code></pre>
+<p>The next value is <code>18</code>. In this example, the sum is <code><em>114</em></code>.</p>
+</article>
+<p>Your puzzle answer was <code>1647269739</code>.</p>
+<article class="day-desc"><h2 id="part2">--- Part Two ---</h2>
+<pre><code>a &lt;&amp;&gt; <em>b</em>
+</code></pre>
+<p>Adding the new values gives <code><em>2</em></code>.</p>
+</article>
+</main></body></html>"#;
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(
+            Puzzle {
+                examples: vec![
+                    "0 3 6 9 12 15\n1 3 6 10 15 21\n".to_string(),
+                    "a <&> b\n".to_string()
+                ],
+                guesses: vec![
+                    Guess {
+                        part: 1,
+                        example: 0,
+                        answer: "114".to_string()
+                    },
+                    Guess {
+                        part: 2,
+                        example: 1,
+                        answer: "2".to_string()
+                    }
+                ]
+            },
+            extract(PAGE)
+        );
+    }
+
+    #[test]
+    fn test_part2_reuses_example() {
+        let page = r#"<article><pre><code>x</code></pre><code><em>1</em></code></article>
+<article><p>Again: <code><em>7</em></code></p></article>"#;
+        let puzzle = extract(page);
+        assert_eq!(1, puzzle.examples.len());
+        assert_eq!(0, puzzle.guesses[1].example);
+        assert_eq!("7", puzzle.guesses[1].answer);
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let template = include_str!("day25.rs").replace("day25", "day9");
+        let filled = fill_template(9, &template, &extract(PAGE)).unwrap();
+        assert!(filled.contains(
+            "const INPUT: &str = \"0 3 6 9 12 15\n1 3 6 10 15 21\";\n    const INPUT2: &str = \"a <&> b\";"
+        ));
+        assert!(filled.contains(
+            "#[test]\n    fn test_day9_part1() {\n        assert_eq!(\"114\", day9_part1(INPUT));"
+        ));
+        assert!(filled.contains("assert_eq!(\"2\", day9_part2(INPUT2));"));
+        assert!(!filled.contains("#[ignore]"));
+        assert_eq!(None, fill_template(9, &filled, &extract(PAGE)));
+    }
+}
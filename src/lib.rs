@@ -0,0 +1,115 @@
+pub mod day1;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
+pub mod day2;
+pub mod day20;
+pub mod day21;
+pub mod day22;
+pub mod day23;
+pub mod day24;
+pub mod day25;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;
+pub mod extract;
+pub mod report;
+pub mod submit;
+pub mod watch;
+
+use day1::*;
+use day10::*;
+use day11::*;
+use day12::*;
+use day13::*;
+use day14::*;
+use day15::*;
+use day16::*;
+use day17::*;
+use day18::*;
+use day19::*;
+use day2::*;
+use day20::*;
+use day21::*;
+use day22::*;
+use day23::*;
+use day24::*;
+use day25::*;
+use day3::*;
+use day4::*;
+use day5::*;
+use day6::*;
+use day7::*;
+use day8::*;
+use day9::*;
+use std::fs::read_to_string;
+
+pub type Part = fn(&str) -> String;
+pub type Parse = fn(&str);
+
+pub static FUNCTIONS: &[(Part, Part)] = &[
+    (day1_part1, day1_part2),
+    (day2_part1, day2_part2),
+    (day3_part1, day3_part2),
+    (day4_part1, day4_part2),
+    (day5_part1, day5_part2),
+    (day6_part1, day6_part2),
+    (day7_part1, day7_part2),
+    (day8_part1, day8_part2),
+    (day9_part1, day9_part2),
+    (day10_part1, day10_part2),
+    (day11_part1, day11_part2),
+    (day12_part1, day12_part2),
+    (day13_part1, day13_part2),
+    (day14_part1, day14_part2),
+    (day15_part1, day15_part2),
+    (day16_part1, day16_part2),
+    (day17_part1, day17_part2),
+    (day18_part1, day18_part2),
+    (day19_part1, day19_part2),
+    (day20_part1, day20_part2),
+    (day21_part1, day21_part2),
+    (day22_part1, day22_part2),
+    (day23_part1, day23_part2),
+    (day24_part1, day24_part2),
+    (day25_part1, day25_part2),
+];
+
+/// The parse step on its own for the days that have a separate one, so it
+/// can be benchmarked apart from the parts.
+pub static PARSERS: &[(u8, Parse)] = &[
+    (2, day2_parse),
+    (3, day3_parse),
+    (4, day4_parse),
+    (5, day5_parse),
+    (6, day6_parse),
+    (7, day7_parse),
+    (8, day8_parse),
+    (9, day9_parse),
+    (10, day10_parse),
+    (11, day11_parse),
+    (12, day12_parse),
+    (13, day13_parse),
+    (14, day14_parse),
+    (16, day16_parse),
+    (17, day17_parse),
+    (18, day18_parse),
+    (19, day19_parse),
+    (20, day20_parse),
+];
+
+pub fn input(day: u8) -> String {
+    read_to_string(format!("./input/day{}.txt", day))
+        .unwrap_or_else(|_| panic!("Should have been able to read input/day{}.txt", day))
+}
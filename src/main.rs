@@ -1,93 +1,15 @@
-mod day1;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day2;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-
-use day1::*;
-use day10::*;
-use day11::*;
-use day12::*;
-use day13::*;
-use day14::*;
-use day15::*;
-use day16::*;
-use day17::*;
-use day18::*;
-use day19::*;
-use day2::*;
-use day20::*;
-use day21::*;
-use day22::*;
-use day23::*;
-use day24::*;
-use day25::*;
-use day3::*;
-use day4::*;
-use day5::*;
-use day6::*;
-use day7::*;
-use day8::*;
-use day9::*;
-use std::{env, fs::read_to_string};
-
-static FUNCTIONS: &'static [(fn(&str) -> String, fn(&str) -> String)] = &[
-    (day1_part1, day1_part2),
-    (day2_part1, day2_part2),
-    (day3_part1, day3_part2),
-    (day4_part1, day4_part2),
-    (day5_part1, day5_part2),
-    (day6_part1, day6_part2),
-    (day7_part1, day7_part2),
-    (day8_part1, day8_part2),
-    (day9_part1, day9_part2),
-    (day10_part1, day10_part2),
-    (day11_part1, day11_part2),
-    (day12_part1, day12_part2),
-    (day13_part1, day13_part2),
-    (day14_part1, day14_part2),
-    (day15_part1, day15_part2),
-    (day16_part1, day16_part2),
-    (day17_part1, day17_part2),
-    (day18_part1, day18_part2),
-    (day19_part1, day19_part2),
-    (day20_part1, day20_part2),
-    (day21_part1, day21_part2),
-    (day22_part1, day22_part2),
-    (day23_part1, day23_part2),
-    (day24_part1, day24_part2),
-    (day25_part1, day25_part2),
-];
+use aoc_in_rust::{day10::PipeMaze, day19::Tree, extract, input, report, submit, watch, FUNCTIONS};
+use std::{
+    env,
+    fs::read_to_string,
+    io::{stdout, IsTerminal},
+    ops::RangeInclusive,
+};
 
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
 
-fn input(day: u8) -> String {
-    read_to_string(format!("./input/day{}.txt", day))
-        .expect(format!("Should have been able to read input/day{}.txt", day).as_str())
-}
-
 fn run_day(day: u8) {
     let input = input(day);
     let (part1, part2) = FUNCTIONS[(day - 1) as usize];
@@ -95,31 +17,145 @@ fn run_day(day: u8) {
     println!("Day {day}, part 2: {}", part2(input.as_str()));
 }
 
+fn measure_days(days: &[u8]) -> Vec<report::Record> {
+    let year = submit::year();
+    let mut records = Vec::new();
+    for &day in days {
+        let input = input(day);
+        let (part1, part2) = FUNCTIONS[(day - 1) as usize];
+        records.push(report::measure(year, day, 1, part1, &input));
+        records.push(report::measure(year, day, 2, part2, &input));
+    }
+    records
+}
+
+/// Whether the answer was right, now or before.
+fn submit_answer(day: u8, part: u8) -> bool {
+    let input = input(day);
+    let (part1, part2) = FUNCTIONS[(day - 1) as usize];
+    let answer = if part == 1 { part1 } else { part2 }(input.as_str());
+    println!("Day {day}, part {part}: submitting {answer}");
+
+    let client = submit::Client::from_env();
+    let mut history = submit::History::load(submit::DEFAULT_HISTORY);
+    match submit::submit(&client, &mut history, day, part, &answer) {
+        Ok(submit::Verdict::Wait(seconds)) => println!("Too soon, wait {seconds}s"),
+        Ok(verdict) => {
+            println!("{verdict}");
+            return matches!(
+                verdict,
+                submit::Verdict::Correct | submit::Verdict::AlreadySolved
+            );
+        }
+        Err(error) => eprintln!("{error}"),
+    }
+    false
+}
+
+fn extract_examples(day: u8, source: &str) {
+    let page = extract::load_page(source).unwrap_or_else(|e| panic!("Couldn't load {source}: {e}"));
+    let puzzle = extract::extract(&page);
+    extract::write_examples(day, &puzzle).expect("Should have been able to write input/examples");
+    for (i, example) in puzzle.examples.iter().enumerate() {
+        println!("{}:\n{}", extract::example_path(day, i).display(), example);
+    }
+    for guess in &puzzle.guesses {
+        println!(
+            "Day {day}, part {}: {} (example {}?)",
+            guess.part,
+            guess.answer,
+            guess.example + 1
+        );
+    }
+
+    let path = format!("./src/day{}.rs", day);
+    let source = read_to_string(&path).expect("Should have been able to read the day's source");
+    match extract::fill_template(day, &source, &puzzle) {
+        Some(filled) => {
+            std::fs::write(&path, filled).expect("Should have been able to write the day's source");
+            println!("Filled in the tests in {path}, double check them");
+        }
+        None => println!("{path} isn't a blank template, left it alone"),
+    }
+}
+
+fn arg(args: &[String], index: usize, range: RangeInclusive<u8>, usage: &str) -> u8 {
+    args.get(index)
+        .and_then(|arg| arg.parse::<u8>().ok())
+        .filter(|arg| range.contains(arg))
+        .expect(usage)
+}
+
 fn main() {
     #[cfg(feature = "dhat-heap")]
     let _profiler = dhat::Profiler::new_heap();
 
-    let args: Vec<String> = env::args().collect();
-    let day_as_string = args.get(1);
-    let day_to_run;
-    if day_as_string.is_some() {
-        day_to_run = Some(
-            day_as_string
-                .unwrap()
-                .parse::<u8>()
-                .expect("Expected the arg to be the day as an integer"),
-        );
-    } else {
-        day_to_run = None;
+    let mut args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("submit") => {
+            let usage = "Usage: submit <day> <part>";
+            if !submit_answer(arg(&args, 2, 1..=25, usage), arg(&args, 3, 1..=2, usage)) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("extract") => {
+            let usage = "Usage: extract <day> <saved page or url>";
+            extract_examples(arg(&args, 2, 1..=25, usage), args.get(3).expect(usage));
+            return;
+        }
+        Some("check") => {
+            let day = arg(&args, 2, 1..=25, "Usage: check <day>");
+            watch::check(day, FUNCTIONS[(day - 1) as usize]);
+            return;
+        }
+        Some("pipes") => {
+            let maze = match args.get(2) {
+                Some(path) => read_to_string(path).expect("Should have been able to read the maze"),
+                None => input(10),
+            };
+            let maze = maze.parse::<PipeMaze>().unwrap_or_else(|e| panic!("{e}"));
+            print!("{}", maze.render(stdout().is_terminal()));
+            return;
+        }
+        Some("workflows") => {
+            let workflows = match args.get(2) {
+                Some(path) => {
+                    read_to_string(path).expect("Should have been able to read the workflows")
+                }
+                None => input(19),
+            };
+            let tree = workflows.parse::<Tree>().unwrap_or_else(|e| panic!("{e}"));
+            print!("{}", tree.to_dot());
+            return;
+        }
+        Some("watch") => {
+            watch::watch(arg(&args, 2, 1..=25, "Usage: watch <day>"));
+            return;
+        }
+        _ => (),
     }
 
-    let days: Vec<u8> = (1..=25).collect();
+    let format = args.iter().position(|arg| arg == "--format").map(|i| {
+        let format = args
+            .get(i + 1)
+            .and_then(|format| format.parse::<report::Format>().ok())
+            .expect("Usage: [day] [--format json|csv|table]");
+        args.drain(i..=i + 1);
+        format
+    });
 
-    if day_to_run.is_some() && days.contains(&day_to_run.unwrap()) {
-        run_day(day_to_run.unwrap());
-    } else {
-        for day in days {
-            run_day(day);
-        }
+    let days = match args.get(1) {
+        Some(day) => vec![day
+            .parse::<u8>()
+            .ok()
+            .filter(|day| (1..=25).contains(day))
+            .expect("Expected the arg to be the day as an integer")],
+        None => (1..=25).collect(),
+    };
+
+    match format {
+        Some(format) => print!("{}", report::render(format, &measure_days(&days))),
+        None => days.into_iter().for_each(run_day),
     }
 }
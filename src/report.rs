@@ -0,0 +1,328 @@
+use std::{fmt::Write, str::FromStr, time::Duration};
+
+use crate::watch::{run_part, Outcome};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Table,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "table" => Ok(Format::Table),
+            _ => Err(format!("unknown format '{s}', expected json, csv or table")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Ok(String),
+    Error(String),
+    Unimplemented,
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Ok(_) => "ok",
+            Status::Error(_) => "error",
+            Status::Unimplemented => "unimplemented",
+        }
+    }
+
+    fn answer(&self) -> Option<&str> {
+        match self {
+            Status::Ok(answer) => Some(answer),
+            _ => None,
+        }
+    }
+}
+
+/// Heap activity while a part ran. Only collected with `--features dhat-heap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocations {
+    pub blocks: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub year: u16,
+    pub day: u8,
+    pub part: u8,
+    pub status: Status,
+    pub duration: Duration,
+    pub allocations: Option<Allocations>,
+}
+
+#[cfg(feature = "dhat-heap")]
+fn heap_totals() -> Option<Allocations> {
+    let stats = dhat::HeapStats::get();
+    Some(Allocations {
+        blocks: stats.total_blocks,
+        bytes: stats.total_bytes,
+    })
+}
+
+#[cfg(not(feature = "dhat-heap"))]
+fn heap_totals() -> Option<Allocations> {
+    None
+}
+
+pub fn measure(year: u16, day: u8, part: u8, function: fn(&str) -> String, input: &str) -> Record {
+    let before = heap_totals();
+    let (outcome, duration) = run_part(function, input);
+    let after = heap_totals();
+
+    let status = match outcome {
+        Outcome::Answer(answer) => Status::Ok(answer),
+        Outcome::Panicked(message) if message.starts_with("not yet implemented") => {
+            Status::Unimplemented
+        }
+        Outcome::Panicked(message) => Status::Error(message),
+    };
+    let allocations = before.zip(after).map(|(before, after)| Allocations {
+        blocks: after.blocks - before.blocks,
+        bytes: after.bytes - before.bytes,
+    });
+    Record {
+        year,
+        day,
+        part,
+        status,
+        duration,
+        allocations,
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json(records: &[Record]) -> String {
+    let lines = records
+        .iter()
+        .map(|r| {
+            let mut line = format!(
+                "  {{\"year\": {}, \"day\": {}, \"part\": {}, \"answer\": {}, \"status\": \"{}\", \"duration_ns\": {}",
+                r.year,
+                r.day,
+                r.part,
+                r.status.answer().map_or("null".to_string(), json_string),
+                r.status.name(),
+                r.duration.as_nanos()
+            );
+            if let Status::Error(message) = &r.status {
+                write!(line, ", \"error\": {}", json_string(message)).unwrap();
+            }
+            match r.allocations {
+                Some(a) => write!(
+                    line,
+                    ", \"allocations\": {}, \"allocated_bytes\": {}}}",
+                    a.blocks, a.bytes
+                ),
+                None => write!(line, ", \"allocations\": null, \"allocated_bytes\": null}}"),
+            }
+            .unwrap();
+            line
+        })
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+fn csv(records: &[Record]) -> String {
+    let mut out =
+        String::from("year,day,part,answer,status,duration_ns,allocations,allocated_bytes\n");
+    for r in records {
+        let (blocks, bytes) = match r.allocations {
+            Some(a) => (a.blocks.to_string(), a.bytes.to_string()),
+            None => (String::new(), String::new()),
+        };
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{}",
+            r.year,
+            r.day,
+            r.part,
+            csv_field(r.status.answer().unwrap_or_default()),
+            r.status.name(),
+            r.duration.as_nanos(),
+            blocks,
+            bytes
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn table(records: &[Record]) -> String {
+    let rows = records
+        .iter()
+        .map(|r| {
+            let answer = match &r.status {
+                Status::Ok(answer) => answer.clone(),
+                Status::Error(message) => message.clone(),
+                Status::Unimplemented => String::new(),
+            };
+            let (blocks, bytes) = match r.allocations {
+                Some(a) => (a.blocks.to_string(), a.bytes.to_string()),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            [
+                r.year.to_string(),
+                r.day.to_string(),
+                r.part.to_string(),
+                r.status.name().to_string(),
+                answer,
+                format!("{:.1?}", r.duration),
+                blocks,
+                bytes,
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = [
+        "year", "day", "part", "status", "answer", "time", "allocs", "bytes",
+    ]
+    .map(String::from);
+
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>();
+        writeln!(out, "{}", cells.join("  ").trim_end()).unwrap();
+    }
+    out
+}
+
+pub fn render(format: Format, records: &[Record]) -> String {
+    match format {
+        Format::Json => json(records),
+        Format::Csv => csv(records),
+        Format::Table => table(records),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record {
+                year: 2023,
+                day: 1,
+                part: 1,
+                status: Status::Ok("142".to_string()),
+                duration: Duration::from_micros(12),
+                allocations: Some(Allocations {
+                    blocks: 3,
+                    bytes: 96,
+                }),
+            },
+            Record {
+                year: 2023,
+                day: 1,
+                part: 2,
+                status: Status::Error("bad \"input\", line 2".to_string()),
+                duration: Duration::from_nanos(500),
+                allocations: None,
+            },
+            Record {
+                year: 2023,
+                day: 22,
+                part: 1,
+                status: Status::Unimplemented,
+                duration: Duration::from_nanos(7),
+                allocations: None,
+            },
+        ]
+    }
+
+    fn unfinished(_input: &str) -> String {
+        todo!();
+    }
+
+    #[test]
+    fn test_measure() {
+        let record = measure(2023, 22, 1, unfinished, "");
+        assert_eq!(Status::Unimplemented, record.status);
+        let record = measure(2023, 1, 1, crate::day1::day1_part1, "1abc2");
+        assert_eq!(Status::Ok("12".to_string()), record.status);
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            r#"[
+  {"year": 2023, "day": 1, "part": 1, "answer": "142", "status": "ok", "duration_ns": 12000, "allocations": 3, "allocated_bytes": 96},
+  {"year": 2023, "day": 1, "part": 2, "answer": null, "status": "error", "duration_ns": 500, "error": "bad \"input\", line 2", "allocations": null, "allocated_bytes": null},
+  {"year": 2023, "day": 22, "part": 1, "answer": null, "status": "unimplemented", "duration_ns": 7, "allocations": null, "allocated_bytes": null}
+]
+"#,
+            render(Format::Json, &records())
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            "year,day,part,answer,status,duration_ns,allocations,allocated_bytes
+2023,1,1,142,ok,12000,3,96
+2023,1,2,,error,500,,
+2023,22,1,,unimplemented,7,,
+",
+            render(Format::Csv, &records())
+        );
+        assert_eq!("\"a,\"\"b\"\"\"", csv_field("a,\"b\""));
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            "year  day  part  status         answer               time     allocs  bytes
+2023  1    1     ok             142                  12.0µs   3       96
+2023  1    2     error          bad \"input\", line 2  500.0ns  -       -
+2023  22   1     unimplemented                       7.0ns    -       -
+",
+            render(Format::Table, &records())
+        );
+    }
+}
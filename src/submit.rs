@@ -0,0 +1,517 @@
+use std::{
+    env, fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+pub const DEFAULT_ENDPOINT: &str = "https://adventofcode.com";
+pub const DEFAULT_YEAR: u16 = 2023;
+pub const DEFAULT_HISTORY: &str = "./.aoc/history.txt";
+
+/// What the server had to say about a submitted answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Correct,
+    TooHigh,
+    TooLow,
+    /// Wrong, but the server didn't say which direction.
+    Wrong,
+    /// Rate limited. Nothing was checked, try again in this many seconds.
+    Wait(u64),
+    AlreadySolved,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "correct"),
+            Verdict::TooHigh => write!(f, "too-high"),
+            Verdict::TooLow => write!(f, "too-low"),
+            Verdict::Wrong => write!(f, "wrong"),
+            Verdict::Wait(seconds) => write!(f, "wait-{seconds}"),
+            Verdict::AlreadySolved => write!(f, "already-solved"),
+        }
+    }
+}
+
+impl FromStr for Verdict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "correct" => Ok(Verdict::Correct),
+            "too-high" => Ok(Verdict::TooHigh),
+            "too-low" => Ok(Verdict::TooLow),
+            "wrong" => Ok(Verdict::Wrong),
+            "already-solved" => Ok(Verdict::AlreadySolved),
+            _ => match s.strip_prefix("wait-").map(|n| n.parse::<u64>()) {
+                Some(Ok(seconds)) => Ok(Verdict::Wait(seconds)),
+                _ => Err(format!("unknown verdict '{s}'")),
+            },
+        }
+    }
+}
+
+/// Pulls the verdict out of the HTML the answer endpoint responds with.
+/// Only the `<article>` is looked at so the rest of the page can't confuse it.
+pub fn parse_response(html: &str) -> Option<Verdict> {
+    let article = match (html.find("<article"), html.find("</article>")) {
+        (Some(start), Some(end)) if start < end => &html[start..end],
+        _ => html,
+    };
+    let text = strip_tags(article).to_lowercase();
+
+    if text.contains("that's the right answer") {
+        Some(Verdict::Correct)
+    } else if text.contains("did you already complete it") {
+        Some(Verdict::AlreadySolved)
+    } else if text.contains("answer too recently") || text.contains("left to wait") {
+        Some(Verdict::Wait(wait_seconds(&text).unwrap_or(60)))
+    } else if text.contains("not the right answer") {
+        if text.contains("too high") {
+            Some(Verdict::TooHigh)
+        } else if text.contains("too low") {
+            Some(Verdict::TooLow)
+        } else {
+            Some(Verdict::Wrong)
+        }
+    } else {
+        None
+    }
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    text
+}
+
+/// "you have 1m 5s left to wait" -> 65
+fn wait_seconds(text: &str) -> Option<u64> {
+    let end = text.find("left to wait")?;
+    let start = text[..end].rfind("you have ")? + "you have ".len();
+    let mut seconds = 0;
+    for part in text[start..end].split_whitespace() {
+        let (number, unit) = part.split_at(part.find(|c: char| !c.is_ascii_digit())?);
+        let number = number.parse::<u64>().ok()?;
+        seconds += match unit {
+            "h" => number * 60 * 60,
+            "m" => number * 60,
+            "s" => number,
+            _ => return None,
+        };
+    }
+    Some(seconds)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub day: u8,
+    pub part: u8,
+    pub answer: String,
+    pub verdict: Verdict,
+}
+
+/// Why an answer wasn't sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Refusal {
+    AlreadyCorrect(String),
+    KnownWrong,
+    NotBelow(String),
+    NotAbove(String),
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::AlreadyCorrect(answer) => write!(f, "already solved with {answer}"),
+            Refusal::KnownWrong => write!(f, "already submitted and it was wrong"),
+            Refusal::NotBelow(answer) => write!(f, "{answer} was too high, answer must be lower"),
+            Refusal::NotAbove(answer) => write!(f, "{answer} was too low, answer must be higher"),
+        }
+    }
+}
+
+/// Every answer that has been sent, one `day part verdict answer` line each.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl History {
+    pub fn load(path: impl AsRef<Path>) -> History {
+        let path = path.as_ref().to_path_buf();
+        let entries = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, '\t');
+                Some(Entry {
+                    day: fields.next()?.parse().ok()?,
+                    part: fields.next()?.parse().ok()?,
+                    verdict: fields.next()?.parse().ok()?,
+                    answer: fields.next()?.to_string(),
+                })
+            })
+            .collect();
+        History { path, entries }
+    }
+
+    pub fn entries(&self, day: u8, part: u8) -> impl Iterator<Item = &Entry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.day == day && entry.part == part)
+    }
+
+    /// Fails if the answer is already known to be right or wrong, or falls
+    /// outside the bracket left by earlier too high / too low answers.
+    pub fn check(&self, day: u8, part: u8, answer: &str) -> Result<(), Refusal> {
+        let value = answer.parse::<i128>().ok();
+        for entry in self.entries(day, part) {
+            let previous = entry.answer.parse::<i128>().ok();
+            match entry.verdict {
+                Verdict::Correct | Verdict::AlreadySolved if entry.answer == answer => {
+                    return Err(Refusal::AlreadyCorrect(entry.answer.clone()))
+                }
+                Verdict::Correct => return Err(Refusal::AlreadyCorrect(entry.answer.clone())),
+                Verdict::TooHigh | Verdict::TooLow | Verdict::Wrong if entry.answer == answer => {
+                    return Err(Refusal::KnownWrong)
+                }
+                Verdict::TooHigh => {
+                    if let (Some(value), Some(previous)) = (value, previous) {
+                        if value >= previous {
+                            return Err(Refusal::NotBelow(entry.answer.clone()));
+                        }
+                    }
+                }
+                Verdict::TooLow => {
+                    if let (Some(value), Some(previous)) = (value, previous) {
+                        if value <= previous {
+                            return Err(Refusal::NotAbove(entry.answer.clone()));
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Rate limit responses aren't kept, they don't say anything about the answer.
+    pub fn record(&mut self, entry: Entry) -> std::io::Result<()> {
+        if let Verdict::Wait(_) = entry.verdict {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}",
+            entry.day, entry.part, entry.verdict, entry.answer
+        )?;
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum SubmitError {
+    Refused(Refusal),
+    /// `AOC_SESSION` is empty, so the server would only say to log in.
+    NoSession,
+    Http(String),
+    UnrecognizedResponse(String),
+    History(std::io::Error),
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitError::Refused(refusal) => write!(f, "not submitting: {refusal}"),
+            SubmitError::NoSession => write!(f, "not submitting: AOC_SESSION isn't set"),
+            SubmitError::Http(error) => write!(f, "request failed: {error}"),
+            SubmitError::UnrecognizedResponse(html) => {
+                write!(f, "couldn't make sense of the response:\n{html}")
+            }
+            SubmitError::History(error) => write!(f, "couldn't save history: {error}"),
+        }
+    }
+}
+
+/// `AOC_YEAR`, or the year these solutions are for.
+pub fn year() -> u16 {
+    env::var("AOC_YEAR")
+        .ok()
+        .and_then(|year| year.parse().ok())
+        .unwrap_or(DEFAULT_YEAR)
+}
+
+pub struct Client {
+    pub endpoint: String,
+    pub year: u16,
+    pub session: String,
+}
+
+impl Client {
+    /// `AOC_ENDPOINT`, `AOC_YEAR` and `AOC_SESSION` (the session cookie).
+    pub fn from_env() -> Client {
+        Client {
+            endpoint: env::var("AOC_ENDPOINT").unwrap_or(DEFAULT_ENDPOINT.to_string()),
+            year: year(),
+            session: env::var("AOC_SESSION").unwrap_or_default(),
+        }
+    }
+
+    pub fn post_answer(&self, day: u8, part: u8, answer: &str) -> Result<Verdict, SubmitError> {
+        if self.session.trim().is_empty() {
+            return Err(SubmitError::NoSession);
+        }
+        let url = format!(
+            "{}/{}/day/{}/answer",
+            self.endpoint.trim_end_matches('/'),
+            self.year,
+            day
+        );
+        let html = ureq::post(&url)
+            .set("Cookie", &format!("session={}", self.session))
+            .send_form(&[("level", &part.to_string()), ("answer", answer)])
+            .map_err(|e| SubmitError::Http(e.to_string()))?
+            .into_string()
+            .map_err(|e| SubmitError::Http(e.to_string()))?;
+        parse_response(&html).ok_or(SubmitError::UnrecognizedResponse(html))
+    }
+}
+
+/// Checks the history, posts the answer and records whatever came back.
+pub fn submit(
+    client: &Client,
+    history: &mut History,
+    day: u8,
+    part: u8,
+    answer: &str,
+) -> Result<Verdict, SubmitError> {
+    history
+        .check(day, part, answer)
+        .map_err(SubmitError::Refused)?;
+    let verdict = client.post_answer(day, part, answer)?;
+    history
+        .record(Entry {
+            day,
+            part,
+            answer: answer.to_string(),
+            verdict,
+        })
+        .map_err(SubmitError::History)?;
+    Ok(verdict)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Read},
+        net::TcpListener,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread::{self, JoinHandle},
+    };
+
+    fn page(message: &str) -> String {
+        format!("<html><body><main><article><p>{message}</p></article></main></body></html>")
+    }
+
+    fn temp_history() -> History {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "aoc-history-{}-{}.txt",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_file(&path);
+        History::load(path)
+    }
+
+    /// Answers every request with `body` and hands back the raw requests it saw.
+    fn mock_server(body: String, requests: usize) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut seen = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut form = vec![0; content_length];
+                reader.read_exact(&mut form).unwrap();
+                request.push_str(&String::from_utf8(form).unwrap());
+                seen.push(request);
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+            seen
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(
+            Some(Verdict::Correct),
+            parse_response(&page(
+                "That's the right answer! You are <em>one gold star</em> closer."
+            ))
+        );
+        assert_eq!(
+            Some(Verdict::TooHigh),
+            parse_response(&page(
+                "That's not the right answer; your answer is too high. If you're stuck..."
+            ))
+        );
+        assert_eq!(
+            Some(Verdict::TooLow),
+            parse_response(&page(
+                "That's not the right answer; your answer is too low."
+            ))
+        );
+        assert_eq!(
+            Some(Verdict::Wrong),
+            parse_response(&page("That's not the right answer. If you're stuck..."))
+        );
+        assert_eq!(
+            Some(Verdict::Wait(125)),
+            parse_response(&page("You gave an answer too recently; you have to wait after submitting an answer before trying again.  You have 2m 5s left to wait."))
+        );
+        assert_eq!(
+            Some(Verdict::Wait(38)),
+            parse_response(&page(
+                "You gave an answer too recently. You have 38s left to wait."
+            ))
+        );
+        assert_eq!(
+            Some(Verdict::AlreadySolved),
+            parse_response(&page(
+                "You don't seem to be solving the right level.  Did you already complete it?"
+            ))
+        );
+        assert_eq!(
+            None,
+            parse_response("<html>Puzzle inputs differ by user.</html>")
+        );
+    }
+
+    #[test]
+    fn test_history_bracket() {
+        let mut history = temp_history();
+        let entry = |answer: &str, verdict| Entry {
+            day: 5,
+            part: 1,
+            answer: answer.to_string(),
+            verdict,
+        };
+        history.record(entry("100", Verdict::TooHigh)).unwrap();
+        history.record(entry("20", Verdict::TooLow)).unwrap();
+        history.record(entry("50", Verdict::Wrong)).unwrap();
+        history.record(entry("60", Verdict::Wait(30))).unwrap();
+
+        assert_eq!(Ok(()), history.check(5, 1, "60"));
+        assert_eq!(Ok(()), history.check(5, 2, "100"));
+        assert_eq!(Err(Refusal::KnownWrong), history.check(5, 1, "50"));
+        assert_eq!(Err(Refusal::KnownWrong), history.check(5, 1, "100"));
+        assert_eq!(
+            Err(Refusal::NotBelow("100".to_string())),
+            history.check(5, 1, "101")
+        );
+        assert_eq!(
+            Err(Refusal::NotAbove("20".to_string())),
+            history.check(5, 1, "3")
+        );
+
+        let reloaded = History::load(&history.path);
+        assert_eq!(3, reloaded.entries(5, 1).count());
+        assert_eq!(
+            Err(Refusal::NotBelow("100".to_string())),
+            reloaded.check(5, 1, "200")
+        );
+        let _ = fs::remove_file(&history.path);
+    }
+
+    #[test]
+    fn test_submit_against_mock_server() {
+        let (endpoint, server) = mock_server(
+            page("That's not the right answer; your answer is too low."),
+            1,
+        );
+        let client = Client {
+            endpoint,
+            year: 2023,
+            session: "cafe".to_string(),
+        };
+        let mut history = temp_history();
+
+        assert_eq!(
+            Verdict::TooLow,
+            submit(&client, &mut history, 6, 2, "1234").unwrap()
+        );
+        // Refused locally, never reaches the server which only answers once.
+        assert!(matches!(
+            submit(&client, &mut history, 6, 2, "1000"),
+            Err(SubmitError::Refused(Refusal::NotAbove(_)))
+        ));
+
+        let requests = server.join().unwrap();
+        assert_eq!(1, requests.len());
+        assert!(requests[0].starts_with("POST /2023/day/6/answer "));
+        assert!(requests[0].contains("session=cafe"));
+        assert!(requests[0].ends_with("level=2&answer=1234"));
+        let _ = fs::remove_file(&history.path);
+    }
+
+    #[test]
+    fn test_submit_without_session() {
+        // Nothing listens here; the request must never be sent.
+        let client = Client {
+            endpoint: "http://127.0.0.1:9".to_string(),
+            year: 2023,
+            session: " ".to_string(),
+        };
+        let mut history = temp_history();
+        assert!(matches!(
+            submit(&client, &mut history, 6, 2, "1234"),
+            Err(SubmitError::NoSession)
+        ));
+        assert_eq!(0, history.entries(6, 2).count());
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    process::Command,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    extract::{answers_path, example_path},
+    Part,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Answer(String),
+    Panicked(String),
+}
+
+/// Runs one part, turning a panic (a `todo!()` most likely) into an outcome.
+/// The panic hook is left alone, so the message still shows up on stderr.
+pub fn run_part(part: Part, input: &str) -> (Outcome, Duration) {
+    let start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| part(input)));
+    let elapsed = start.elapsed();
+
+    let outcome = match result {
+        Ok(answer) => Outcome::Answer(answer),
+        Err(payload) => Outcome::Panicked(
+            payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or(payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default(),
+        ),
+    };
+    (outcome, elapsed)
+}
+
+/// `(part, example, answer)` from the answers file `extract` writes.
+pub fn expected_answers(day: u8) -> Vec<(u8, usize, String)> {
+    fs::read_to_string(answers_path(day))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            Some((
+                fields.next()?.parse().ok()?,
+                fields.next()?.parse::<usize>().ok()?.checked_sub(1)?,
+                fields.next()?.to_string(),
+            ))
+        })
+        .collect()
+}
+
+fn summary_line(label: &str, part: u8, outcome: &Outcome, elapsed: Duration) -> String {
+    let result = match outcome {
+        Outcome::Answer(answer) => answer.clone(),
+        Outcome::Panicked(message) => format!("panicked: {message}"),
+    };
+    format!("  {label:<10} part {part}  {result}  ({elapsed:.1?})")
+}
+
+/// Runs the day's own unit tests, which hold the examples for days that
+/// predate `extract`.
+fn run_unit_tests(day: u8) -> bool {
+    let cargo = env::var("CARGO").unwrap_or("cargo".to_string());
+    let filter = format!("day{day}::");
+    let status = Command::new(&cargo)
+        .args(["test", "--quiet", "--lib", &filter])
+        .status();
+    match status {
+        Ok(status) => status.success(),
+        Err(e) => {
+            println!("✘ couldn't run {cargo}: {e}");
+            false
+        }
+    }
+}
+
+/// The examples with known answers first, then the real input. Without an
+/// answers file the day's unit tests stand in for the examples.
+pub fn check(day: u8, (part1, part2): (Part, Part)) {
+    println!("Day {day}");
+    let answers = expected_answers(day);
+    let mut passed = true;
+    if answers.is_empty() {
+        println!(
+            "  no {}, running the unit tests",
+            answers_path(day).display()
+        );
+        passed = run_unit_tests(day);
+    }
+    for (part, example, expected) in answers {
+        let Ok(input) = fs::read_to_string(example_path(day, example)) else {
+            continue;
+        };
+        let (outcome, elapsed) = run_part(if part == 1 { part1 } else { part2 }, &input);
+        let ok = outcome == Outcome::Answer(expected.clone());
+        passed &= ok;
+        let label = format!("example {}", example + 1);
+        let mark = if ok { "✔" } else { "✘" };
+        let mut line = summary_line(&label, part, &outcome, elapsed);
+        if !ok {
+            line.push_str(&format!("  expected {expected}"));
+        }
+        println!("{mark}{line}");
+    }
+
+    if !passed {
+        println!("  examples failing, skipping the real input");
+        return;
+    }
+    let Ok(input) = fs::read_to_string(format!("./input/day{}.txt", day)) else {
+        println!("  no input/day{}.txt", day);
+        return;
+    };
+    for (part, function) in [(1, part1), (2, part2)] {
+        let (outcome, elapsed) = run_part(function, &input);
+        println!(" {}", summary_line("input", part, &outcome, elapsed));
+    }
+}
+
+fn watched_files(day: u8) -> Vec<PathBuf> {
+    let mut files = vec![
+        PathBuf::from(format!("./src/day{}.rs", day)),
+        PathBuf::from(format!("./input/day{}.txt", day)),
+        answers_path(day),
+    ];
+    files.extend(
+        (0..)
+            .map(|n| example_path(day, n))
+            .take_while(|p| p.exists()),
+    );
+    files
+}
+
+fn last_modified(files: &[PathBuf]) -> Option<SystemTime> {
+    files
+        .iter()
+        .filter_map(|file| fs::metadata(file).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/// Polls the day's source, input and examples. Every change rebuilds and
+/// runs `check` with the fresh binary.
+pub fn watch(day: u8) {
+    let cargo = env::var("CARGO").unwrap_or("cargo".to_string());
+    let mut seen = None;
+    loop {
+        let modified = last_modified(&watched_files(day));
+        if modified != seen {
+            seen = modified;
+            // Clear the screen and home the cursor so each run replaces the last.
+            print!("\x1b[2J\x1b[H");
+            let status = Command::new(&cargo)
+                .args(["run", "--quiet", "--", "check", &day.to_string()])
+                .status();
+            match status {
+                Ok(status) if status.success() => (),
+                Ok(_) => println!("✘ build or run failed"),
+                Err(e) => println!("✘ couldn't run {cargo}: {e}"),
+            }
+            println!("\nwatching day {day}, ctrl-c to stop");
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn answer(input: &str) -> String {
+        input.len().to_string()
+    }
+
+    fn unfinished(_input: &str) -> String {
+        todo!();
+    }
+
+    #[test]
+    fn test_run_part() {
+        assert_eq!(Outcome::Answer("3".to_string()), run_part(answer, "abc").0);
+        assert_eq!(
+            Outcome::Panicked("not yet implemented".to_string()),
+            run_part(unfinished, "abc").0
+        );
+    }
+}